@@ -0,0 +1,216 @@
+use bevy::prelude::*;
+use smart_default::SmartDefault;
+
+use super::{GameCamera, GameCameraTarget, target_entity};
+use crate::engine::character::AttackEvent;
+use crate::engine::character::controller::CharacterController;
+
+/// Trauma based shake, offset and roll grow with square of the trauma,
+/// so small hits are subtle and big ones are violent.
+#[derive(SmartDefault, Clone, Reflect, Debug)]
+pub struct CameraShake {
+    /// Current shake strength from 0 to 1.
+    pub trauma: f32,
+    /// Trauma removed per second.
+    #[default(1.0)]
+    pub decay: f32,
+    #[default(0.5)]
+    pub max_offset: f32,
+    /// Maximum roll in radians.
+    #[default(0.05)]
+    pub max_roll: f32,
+    #[default(20.0)]
+    pub frequency: f32,
+    /// Trauma added when the followed entity is attacked.
+    #[default(0.3)]
+    pub hit_trauma: f32,
+    #[reflect(ignore)]
+    time: f32,
+    #[reflect(ignore)]
+    applied: (Vec3, f32),
+}
+
+impl CameraShake {
+    pub fn add_trauma(&mut self, amount: f32) {
+        self.trauma = (self.trauma + amount).clamp(0.0, 1.0);
+    }
+
+    fn offset(&self) -> (Vec3, f32) {
+        let shake = self.trauma * self.trauma;
+        let t = self.time * self.frequency;
+
+        // sum of sines with unrelated frequencies looks random but stays smooth
+        let noise = |seed: f32| ((t + seed).sin() + (t * 1.7 + seed * 3.1).sin() * 0.5) / 1.5;
+        let offset = Vec3::new(noise(0.0), noise(11.0), 0.0) * self.max_offset * shake;
+        (offset, noise(23.0) * self.max_roll * shake)
+    }
+}
+
+/// Adds trauma to the shake of the game camera, for explosions and other events
+/// not caused by an attack on the followed entity.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct CameraShakeEvent {
+    pub trauma: f32,
+}
+
+/// Smooths changes of [`GameCamera::distance`] caused by zoom input.
+#[derive(SmartDefault, Clone, Reflect, Debug)]
+pub struct CameraZoom {
+    #[default(8.0)]
+    pub rate: f32,
+    /// Distance actually used by the camera, follows [`GameCamera::distance`].
+    pub current: f32,
+}
+
+/// Moves the camera focus ahead of the followed character, so it sees where it is going.
+#[derive(SmartDefault, Clone, Reflect, Debug)]
+pub struct CameraLookAhead {
+    /// Seconds of movement with the current velocity to look ahead.
+    #[default(0.4)]
+    pub lead_time: f32,
+    #[default(3.0)]
+    pub max_distance: f32,
+    #[default(3.0)]
+    pub rate: f32,
+    pub offset: Vec3,
+}
+
+/// Keeps the followed entity and its opponent on screen, by moving focus between them
+/// and pulling the camera out when they are far apart.
+#[derive(SmartDefault, Clone, Reflect, Debug)]
+pub struct CombatFraming {
+    #[default(true)]
+    pub enabled: bool,
+    /// Entity that last attacked or was attacked by the followed entity.
+    pub opponent: Option<Entity>,
+    /// Seconds after the last attack for which the opponent is framed.
+    #[default(5.0)]
+    pub memory: f32,
+    pub remaining: f32,
+    /// Opponents further than this are not framed.
+    #[default(25.0)]
+    pub max_distance: f32,
+    /// Space around both entities kept on screen.
+    #[default(3.0)]
+    pub padding: f32,
+    #[default(3.0)]
+    pub rate: f32,
+    pub offset: Vec3,
+    pub extra_distance: f32,
+}
+
+pub(super) fn update_camera_effects(
+    camera: Single<(&mut GameCamera, &Projection)>,
+    targets: Query<Entity, With<GameCameraTarget>>,
+    transforms: Query<&Transform, Without<GameCamera>>,
+    controllers: Query<&CharacterController>,
+    mut attacks: EventReader<AttackEvent>,
+    mut shakes: EventReader<CameraShakeEvent>,
+    time: Res<Time>,
+) {
+    let (mut camera, projection) = camera.into_inner();
+    let camera = camera.as_mut();
+    let delta = time.delta_secs();
+    let target = target_entity(camera, &targets, &transforms);
+
+    let zoom = &mut camera.zoom;
+    if zoom.current <= 0.0 {
+        zoom.current = camera.distance;
+    } else {
+        zoom.current
+            .smooth_nudge(&camera.distance, zoom.rate, delta);
+    }
+
+    let look_ahead = &mut camera.look_ahead;
+    let velocity = target
+        .and_then(|target| controllers.get(target).ok())
+        .map_or(Vec3::ZERO, |controller| {
+            Vec3::new(controller.velocity.x, 0.0, controller.velocity.z)
+        });
+    let desired = (velocity * look_ahead.lead_time).clamp_length_max(look_ahead.max_distance);
+    look_ahead
+        .offset
+        .smooth_nudge(&desired, look_ahead.rate, delta);
+
+    for shake in shakes.read() {
+        camera.shake.add_trauma(shake.trauma);
+    }
+
+    let framing = &mut camera.framing;
+    for attack in attacks.read() {
+        let opponent = match target {
+            Some(target) if attack.target == target => {
+                camera.shake.add_trauma(camera.shake.hit_trauma);
+                attack.attacker
+            }
+            Some(target) if attack.attacker == Some(target) => Some(attack.target),
+            _ => continue,
+        };
+        if let Some(opponent) = opponent {
+            framing.opponent = Some(opponent);
+            framing.remaining = framing.memory;
+        }
+    }
+
+    framing.remaining = (framing.remaining - delta).max(0.0);
+    let positions = target
+        .zip(framing.opponent)
+        .filter(|_| framing.enabled && framing.remaining > 0.0)
+        .and_then(|(target, opponent)| {
+            Some((transforms.get(target).ok()?, transforms.get(opponent).ok()?))
+        })
+        .map(|(target, opponent)| (target.translation, opponent.translation))
+        .filter(|(target, opponent)| target.distance(*opponent) <= framing.max_distance);
+
+    let (desired_offset, desired_extra) = match positions {
+        Some((target, opponent)) => {
+            let fov = match projection {
+                Projection::Perspective(perspective) => perspective.fov,
+                Projection::Orthographic(_) => std::f32::consts::FRAC_PI_4,
+            };
+            let half_size = target.distance(opponent) * 0.5 + framing.padding;
+            let needed = half_size / (fov * 0.5).tan();
+            (
+                (opponent - target) * 0.5,
+                (needed - camera.zoom.current).max(0.0),
+            )
+        }
+        None => {
+            framing.opponent = None;
+            (Vec3::ZERO, 0.0)
+        }
+    };
+    framing
+        .offset
+        .smooth_nudge(&desired_offset, framing.rate, delta);
+    framing
+        .extra_distance
+        .smooth_nudge(&desired_extra, framing.rate, delta);
+
+    let shake = &mut camera.shake;
+    shake.trauma = (shake.trauma - shake.decay * delta).max(0.0);
+    shake.time += delta;
+}
+
+/// Removes last frame shake, so the camera moves from its undisturbed pose.
+pub(super) fn clear_camera_shake(camera: Single<(&mut Transform, &mut GameCamera)>) {
+    let (mut transform, mut camera) = camera.into_inner();
+    let (offset, roll) = std::mem::take(&mut camera.shake.applied);
+    if offset != Vec3::ZERO || roll != 0.0 {
+        transform.translation -= offset;
+        transform.rotate_local_z(-roll);
+    }
+}
+
+pub(super) fn apply_camera_shake(camera: Single<(&mut Transform, &mut GameCamera)>) {
+    let (mut transform, mut camera) = camera.into_inner();
+    if camera.shake.trauma <= 0.0 {
+        return;
+    }
+
+    let (offset, roll) = camera.shake.offset();
+    let offset = transform.rotation * offset;
+    transform.translation += offset;
+    transform.rotate_local_z(roll);
+    camera.shake.applied = (offset, roll);
+}
@@ -0,0 +1,383 @@
+pub mod effects;
+pub mod track;
+
+use bevy::picking::mesh_picking::ray_cast::{RayCastSettings, RayCastVisibility};
+use bevy::prelude::*;
+use effects::{
+    CameraLookAhead, CameraShake, CameraShakeEvent, CameraZoom, CombatFraming, apply_camera_shake,
+    clear_camera_shake, update_camera_effects,
+};
+use smart_default::SmartDefault;
+use track::CameraTrack;
+
+use super::character::Character;
+use super::input::{GameplayInput, GameplayInputSet};
+use super::item::Item;
+
+pub struct GameCameraPlugin;
+
+impl Plugin for GameCameraPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<GameCamera>();
+        app.register_type::<GameCameraTarget>();
+        app.register_type::<CameraObstruction>();
+        app.register_type::<CameraMode>();
+        app.add_event::<CameraShakeEvent>();
+        app.add_systems(PreUpdate, rotate_camera.after(GameplayInputSet));
+        app.add_systems(
+            Update,
+            (
+                clear_camera_shake,
+                update_camera_effects,
+                update_camera,
+                fade_occluders,
+                apply_camera_shake,
+            )
+                .chain(),
+        );
+    }
+}
+
+/// Game camera driven by its [`CameraMode`], yaw 0 looks towards negative Z.
+/// Use [`GameCamera::set_mode`] and [`GameCamera::set_target`] to get blended transitions.
+#[derive(Component, SmartDefault, Reflect, Debug)]
+#[reflect(Component)]
+#[require(Transform, Camera3d)]
+pub struct GameCamera {
+    mode: CameraMode,
+    /// Followed entity, first [`GameCameraTarget`] is used when it is not set or does not exist.
+    target: Option<Entity>,
+    /// Duration of transitions between modes and targets, in seconds.
+    #[default(1.0)]
+    pub blend_duration: f32,
+    #[reflect(ignore)]
+    transition: Option<CameraTransition>,
+    pub yaw: f32,
+    /// Angle above the horizon, in radians.
+    #[default(1.19)]
+    pub pitch: f32,
+    #[default(0.2)]
+    pub min_pitch: f32,
+    #[default(1.45)]
+    pub max_pitch: f32,
+    #[default(20.0)]
+    pub distance: f32,
+    #[default(5.0)]
+    pub min_distance: f32,
+    #[default(50.0)]
+    pub max_distance: f32,
+    #[default(5.0)]
+    pub smooth_rate: f32,
+    pub obstruction: CameraObstruction,
+    /// Free-fly speed in units per second, doubled while sprinting.
+    #[default(15.0)]
+    pub fly_speed: f32,
+    pub zoom: CameraZoom,
+    pub look_ahead: CameraLookAhead,
+    pub framing: CombatFraming,
+    pub shake: CameraShake,
+}
+
+impl GameCamera {
+    pub fn mode(&self) -> &CameraMode {
+        &self.mode
+    }
+
+    pub fn set_mode(&mut self, mode: CameraMode) {
+        self.mode = mode;
+        self.transition = Some(CameraTransition::default());
+    }
+
+    pub fn target(&self) -> Option<Entity> {
+        self.target
+    }
+
+    pub fn set_target(&mut self, target: Option<Entity>) {
+        self.target = target;
+        self.transition = Some(CameraTransition::default());
+    }
+
+    /// Follow distance after smoothing of zoom and combat framing.
+    pub fn view_distance(&self) -> f32 {
+        self.zoom.current + self.framing.extra_distance
+    }
+
+    /// Whether movement input drives the camera instead of the player.
+    pub fn captures_movement(&self) -> bool {
+        matches!(self.mode, CameraMode::FreeFly { .. })
+    }
+
+    /// Direction from the target to the camera.
+    pub fn direction(&self) -> Dir3 {
+        Dir3::new(Vec3::new(
+            self.yaw.sin() * self.pitch.cos(),
+            self.pitch.sin(),
+            self.yaw.cos() * self.pitch.cos(),
+        ))
+        .unwrap_or(Dir3::Y)
+    }
+}
+
+#[derive(Clone, Default, Reflect, Debug)]
+pub enum CameraMode {
+    /// Orbits the target, rotated and zoomed by gameplay input.
+    #[default]
+    Follow,
+    /// Debug camera moved by movement input, looking along yaw and pitch.
+    FreeFly {
+        translation: Vec3,
+    },
+    Fixed {
+        translation: Vec3,
+        look_at: Vec3,
+    },
+    Track(CameraTrack),
+}
+
+/// Blend from the pose the camera had when mode or target changed.
+#[derive(Clone, Default, Debug)]
+struct CameraTransition {
+    from: Option<Transform>,
+    elapsed: f32,
+}
+
+/// How the camera deals with world geometry between itself and the target.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Reflect, Debug)]
+pub enum CameraObstruction {
+    /// Camera moves in front of the obstacle.
+    #[default]
+    PullIn,
+    /// Camera keeps its distance and obstacles become transparent.
+    Fade,
+    Ignore,
+}
+
+#[derive(Component, Default, Clone, Reflect, Debug)]
+#[require(Transform)]
+pub struct GameCameraTarget;
+
+/// Original material of an entity made transparent by [`CameraObstruction::Fade`].
+#[derive(Component)]
+struct Faded(Handle<StandardMaterial>);
+
+/// Distance kept between the camera and the obstacle it was pulled in front of.
+const OBSTRUCTION_MARGIN: f32 = 0.3;
+
+const FADED_ALPHA: f32 = 0.3;
+
+fn rotate_camera(
+    mut camera: Single<&mut GameCamera>,
+    mut input: ResMut<GameplayInput>,
+    time: Res<Time>,
+) {
+    if input.zoom != 0.0 {
+        camera.distance =
+            (camera.distance - input.zoom).clamp(camera.min_distance, camera.max_distance);
+    }
+
+    if input.camera_rotation != Vec2::ZERO {
+        camera.yaw = (camera.yaw + input.camera_rotation.x).rem_euclid(std::f32::consts::TAU);
+        camera.pitch =
+            (camera.pitch + input.camera_rotation.y).clamp(camera.min_pitch, camera.max_pitch);
+    }
+    input.view_yaw = camera.yaw;
+
+    let speed = if input.sprint {
+        camera.fly_speed * 2.0
+    } else {
+        camera.fly_speed
+    };
+    let rotation = Transform::IDENTITY
+        .looking_to(-camera.direction(), Vec3::Y)
+        .rotation;
+
+    match &mut camera.mode {
+        CameraMode::FreeFly { translation } => {
+            let direction = rotation * Vec3::new(input.movement.x, 0.0, -input.movement.y);
+            *translation += direction * speed * time.delta_secs();
+        }
+        CameraMode::Track(track) => track.elapsed += time.delta_secs(),
+        CameraMode::Follow | CameraMode::Fixed { .. } => {}
+    }
+}
+
+fn update_camera(
+    camera: Single<(&mut Transform, &mut GameCamera)>,
+    targets: Query<Entity, With<GameCameraTarget>>,
+    transforms: Query<&Transform, Without<GameCamera>>,
+    obstacles: Query<(), (Without<Character>, Without<Item>)>,
+    mut ray_cast: MeshRayCast,
+    time: Res<Time>,
+) {
+    let (mut camera_transform, mut camera) = camera.into_inner();
+
+    let target = focus_position(&camera, &targets, &transforms);
+
+    let pose = match &camera.mode {
+        CameraMode::Follow => target.map(|target| {
+            let distance = obstructed_distance(&camera, target, &obstacles, &mut ray_cast);
+            let translation = target + camera.direction() * distance;
+            (
+                Transform::from_translation(translation).looking_at(target, Vec3::Y),
+                distance < camera.view_distance(),
+            )
+        }),
+        CameraMode::FreeFly { translation } => Some((
+            Transform::from_translation(*translation).looking_to(-camera.direction(), Vec3::Y),
+            true,
+        )),
+        CameraMode::Fixed {
+            translation,
+            look_at,
+        } => Some((
+            Transform::from_translation(*translation).looking_at(*look_at, Vec3::Y),
+            true,
+        )),
+        CameraMode::Track(track) => track.pose().map(|pose| (pose, true)),
+    };
+
+    // without target or keyframes the camera stays where it is
+    let Some((pose, exact)) = pose else {
+        return;
+    };
+
+    let blend_duration = camera.blend_duration;
+    if let Some(transition) = &mut camera.transition {
+        let from = *transition.from.get_or_insert(*camera_transform);
+        transition.elapsed += time.delta_secs();
+
+        let t = (transition.elapsed / blend_duration.max(f32::EPSILON)).clamp(0.0, 1.0);
+        let t = t * t * (3.0 - 2.0 * t);
+        camera_transform.translation = from.translation.lerp(pose.translation, t);
+        camera_transform.rotation = from.rotation.slerp(pose.rotation, t);
+
+        if t >= 1.0 {
+            camera.transition = None;
+        }
+        return;
+    }
+
+    if exact {
+        // snap in front of obstacles too, smoothing would show them for a few frames
+        *camera_transform = pose;
+    } else if let Some(target) = target {
+        camera_transform.translation.smooth_nudge(
+            &pose.translation,
+            camera.smooth_rate,
+            time.delta_secs(),
+        );
+        camera_transform.look_at(target, Vec3::Y);
+    }
+}
+
+fn target_entity(
+    camera: &GameCamera,
+    targets: &Query<Entity, With<GameCameraTarget>>,
+    transforms: &Query<&Transform, Without<GameCamera>>,
+) -> Option<Entity> {
+    camera
+        .target
+        .filter(|target| transforms.contains(*target))
+        .or_else(|| targets.iter().next())
+}
+
+/// Point the follow camera looks at, target moved by look-ahead and combat framing.
+fn focus_position(
+    camera: &GameCamera,
+    targets: &Query<Entity, With<GameCameraTarget>>,
+    transforms: &Query<&Transform, Without<GameCamera>>,
+) -> Option<Vec3> {
+    target_entity(camera, targets, transforms)
+        .and_then(|target| transforms.get(target).ok())
+        .map(|transform| transform.translation + camera.look_ahead.offset + camera.framing.offset)
+}
+
+/// Distance from the target at which the camera is not hidden behind an obstacle.
+fn obstructed_distance(
+    camera: &GameCamera,
+    target: Vec3,
+    obstacles: &Query<(), (Without<Character>, Without<Item>)>,
+    ray_cast: &mut MeshRayCast,
+) -> f32 {
+    if camera.obstruction != CameraObstruction::PullIn {
+        return camera.view_distance();
+    }
+
+    let filter = |entity| obstacles.contains(entity);
+    let settings = RayCastSettings::default()
+        .with_visibility(RayCastVisibility::Visible)
+        .with_filter(&filter);
+    ray_cast
+        .cast_ray(Ray3d::new(target, camera.direction()), &settings)
+        .first()
+        .map(|(_, hit)| hit.distance)
+        .filter(|hit| *hit < camera.view_distance())
+        .map_or(camera.view_distance(), |hit| {
+            (hit - OBSTRUCTION_MARGIN).max(OBSTRUCTION_MARGIN)
+        })
+}
+
+fn fade_occluders(
+    mut commands: Commands,
+    camera: Single<(&Transform, &GameCamera)>,
+    targets: Query<Entity, With<GameCameraTarget>>,
+    transforms: Query<&Transform, Without<GameCamera>>,
+    obstacles: Query<(), (Without<Character>, Without<Item>)>,
+    mut meshes: Query<(&mut MeshMaterial3d<StandardMaterial>, Has<Faded>)>,
+    faded: Query<(Entity, &Faded)>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut ray_cast: MeshRayCast,
+) {
+    let (camera_transform, camera) = *camera;
+
+    let target = focus_position(camera, &targets, &transforms)
+        .filter(|_| camera.obstruction == CameraObstruction::Fade)
+        .filter(|_| matches!(camera.mode, CameraMode::Follow));
+
+    let mut occluders = Vec::new();
+    if let Some(target) = target {
+        let offset = camera_transform.translation - target;
+        let filter = |entity| obstacles.contains(entity);
+        let settings = RayCastSettings::default()
+            .with_visibility(RayCastVisibility::Visible)
+            .with_filter(&filter)
+            .never_early_exit();
+        occluders = ray_cast
+            .cast_ray(
+                Ray3d::new(target, Dir3::new(offset).unwrap_or(Dir3::Y)),
+                &settings,
+            )
+            .iter()
+            .filter(|(_, hit)| hit.distance < offset.length())
+            .map(|(entity, _)| *entity)
+            .collect::<Vec<_>>();
+    }
+
+    for entity in occluders.iter() {
+        let Ok((mut material, false)) = meshes.get_mut(*entity) else {
+            continue;
+        };
+
+        // faded copy is per entity, materials are often shared
+        let Some(mut transparent) = materials.get(&material.0).cloned() else {
+            continue;
+        };
+        transparent.base_color.set_alpha(FADED_ALPHA);
+        transparent.alpha_mode = AlphaMode::Blend;
+
+        commands.entity(*entity).insert(Faded(material.0.clone()));
+        material.0 = materials.add(transparent);
+    }
+
+    for (entity, Faded(original)) in faded.iter() {
+        if occluders.contains(&entity) {
+            continue;
+        }
+
+        if let Ok((mut material, _)) = meshes.get_mut(entity) {
+            materials.remove(&material.0);
+            material.0 = original.clone();
+        }
+        commands.entity(entity).remove::<Faded>();
+    }
+}
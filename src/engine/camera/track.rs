@@ -0,0 +1,90 @@
+use bevy::prelude::*;
+
+#[derive(Clone, Copy, Reflect, Debug)]
+pub struct CameraKeyframe {
+    /// Seconds from the start of the track.
+    pub time: f32,
+    pub translation: Vec3,
+    pub look_at: Vec3,
+}
+
+impl CameraKeyframe {
+    pub fn from_transform(time: f32, transform: &Transform) -> Self {
+        Self {
+            time,
+            translation: transform.translation,
+            look_at: transform.translation + *transform.forward(),
+        }
+    }
+}
+
+/// Camera path through keyframes, positions and look targets are interpolated
+/// with Catmull-Rom spline so the camera passes through every keyframe smoothly.
+#[derive(Clone, Default, Reflect, Debug)]
+pub struct CameraTrack {
+    keyframes: Vec<CameraKeyframe>,
+    pub looping: bool,
+    pub elapsed: f32,
+}
+
+impl CameraTrack {
+    pub fn new(mut keyframes: Vec<CameraKeyframe>, looping: bool) -> Self {
+        keyframes.sort_by(|a, b| a.time.total_cmp(&b.time));
+        Self {
+            keyframes,
+            looping,
+            elapsed: 0.0,
+        }
+    }
+
+    pub fn duration(&self) -> f32 {
+        self.keyframes.last().map_or(0.0, |k| k.time)
+    }
+
+    pub fn pose(&self) -> Option<Transform> {
+        let keyframes = &self.keyframes;
+        let last = keyframes.len().checked_sub(1)?;
+
+        let time = if self.looping && self.duration() > 0.0 {
+            self.elapsed.rem_euclid(self.duration())
+        } else {
+            self.elapsed.clamp(0.0, self.duration())
+        };
+
+        // segment between keyframes `index` and `index + 1`
+        let index = keyframes
+            .iter()
+            .rposition(|k| k.time <= time)
+            .unwrap_or(0)
+            .min(last.saturating_sub(1));
+        let (start, end) = (&keyframes[index], &keyframes[(index + 1).min(last)]);
+        let length = end.time - start.time;
+        let t = if length > 0.0 {
+            ((time - start.time) / length).clamp(0.0, 1.0)
+        } else {
+            1.0
+        };
+
+        let point =
+            |offset: isize| &keyframes[(index as isize + offset).clamp(0, last as isize) as usize];
+        let (p0, p1, p2, p3) = (point(-1), point(0), point(1), point(2));
+
+        let translation = catmull_rom(
+            p0.translation,
+            p1.translation,
+            p2.translation,
+            p3.translation,
+            t,
+        );
+        let look_at = catmull_rom(p0.look_at, p1.look_at, p2.look_at, p3.look_at, t);
+        Some(Transform::from_translation(translation).looking_at(look_at, Vec3::Y))
+    }
+}
+
+fn catmull_rom(p0: Vec3, p1: Vec3, p2: Vec3, p3: Vec3, t: f32) -> Vec3 {
+    let (t2, t3) = (t * t, t * t * t);
+    0.5 * ((2.0 * p1)
+        + (p2 - p0) * t
+        + (2.0 * p0 - 5.0 * p1 + 4.0 * p2 - p3) * t2
+        + (3.0 * p1 - p0 - 3.0 * p2 + p3) * t3)
+}
@@ -0,0 +1,99 @@
+use bevy::prelude::*;
+use smart_default::SmartDefault;
+
+use super::CharacterSet;
+use super::controller::CharacterController;
+use crate::engine::spatial::SpatialHash;
+
+pub struct AvoidancePlugin;
+
+impl Plugin for AvoidancePlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<Avoidance>();
+        app.register_type::<AvoidanceSettings>();
+        app.init_resource::<AvoidanceSettings>();
+        app.add_systems(FixedUpdate, separate_agents.in_set(CharacterSet::Avoidance));
+    }
+}
+
+/// Steers the character away from neighbours before their capsules collide.
+#[derive(Component, SmartDefault, Clone, Reflect, Debug)]
+pub struct Avoidance {
+    /// Personal space, should be a bit larger than [`CharacterController::radius`].
+    #[default(0.75)]
+    pub radius: f32,
+    /// Maximum speed in units per second added to the desired velocity to keep the distance.
+    #[default(4.0)]
+    pub strength: f32,
+}
+
+/// Limits that keep the cost of avoidance linear in the number of agents.
+#[derive(Resource, SmartDefault, Reflect, Debug)]
+#[reflect(Resource)]
+pub struct AvoidanceSettings {
+    #[default(2.0)]
+    pub cell_size: f32,
+    #[default(8)]
+    pub max_neighbours: usize,
+    #[default(1.0)]
+    pub max_agent_radius: f32,
+}
+
+fn separate_agents(
+    mut agents: Query<(Entity, &Transform, &Avoidance, &mut CharacterController)>,
+    mut hash: Local<SpatialHash<(Entity, f32)>>,
+    mut velocities: Local<Vec<Vec3>>,
+    settings: Res<AvoidanceSettings>,
+) {
+    hash.clear(settings.cell_size);
+    for (entity, transform, avoidance, _) in agents.iter() {
+        hash.insert(transform.translation, (entity, avoidance.radius));
+    }
+
+    velocities.clear();
+    for (entity, transform, avoidance, _) in agents.iter() {
+        let position = transform.translation;
+        let search_radius = avoidance.radius + settings.max_agent_radius;
+
+        let mut push = Vec3::ZERO;
+        let mut neighbours = 0;
+        for (other_position, (other, other_radius)) in hash.nearby(position, search_radius) {
+            if *other == entity {
+                continue;
+            }
+
+            let mut difference = position - *other_position;
+            difference.y = 0.0;
+            let distance = difference.length();
+            let overlap = avoidance.radius + other_radius - distance;
+            if overlap <= 0.0 {
+                continue;
+            }
+
+            let direction = if distance > f32::EPSILON {
+                difference / distance
+            } else {
+                // agents standing at the same spot, split them based on entity order
+                if entity < *other {
+                    Vec3::X
+                } else {
+                    Vec3::NEG_X
+                }
+            };
+            push += direction * overlap * 0.5;
+
+            neighbours += 1;
+            if neighbours >= settings.max_neighbours {
+                break;
+            }
+        }
+
+        velocities.push((push / avoidance.radius).clamp_length_max(1.0) * avoidance.strength);
+    }
+
+    for ((_, _, _, mut controller), velocity) in agents.iter_mut().zip(velocities.iter()) {
+        if *velocity != Vec3::ZERO {
+            controller.desired_velocity += *velocity;
+        }
+    }
+}
@@ -0,0 +1,289 @@
+use bevy::math::bounding::Aabb3d;
+use bevy::picking::mesh_picking::ray_cast::{
+    Backfaces, RayMeshHit, ray_aabb_intersection_3d, ray_mesh_intersection,
+};
+use bevy::prelude::*;
+use bevy::render::mesh::{Indices, PrimitiveTopology};
+use bevy::render::primitives::Aabb;
+use smart_default::SmartDefault;
+
+use super::CharacterSet;
+use crate::engine::spatial::SpatialHash;
+
+pub struct CharacterControllerPlugin;
+
+impl Plugin for CharacterControllerPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<CharacterController>();
+        app.register_type::<Collider>();
+        app.register_type::<Ground>();
+        app.add_systems(FixedUpdate, move_characters.in_set(CharacterSet::Physics));
+    }
+}
+
+/// Kinematic capsule that moves the character, translation is the center of the capsule.
+/// Movement systems only set [`CharacterController::desired_velocity`],
+/// actual velocity follows it with limited acceleration.
+#[derive(Component, SmartDefault, Reflect, Debug)]
+pub struct CharacterController {
+    pub desired_velocity: Vec3,
+    pub velocity: Vec3,
+    #[default(0.5)]
+    pub radius: f32,
+    #[default(2.0)]
+    pub height: f32,
+    #[default(40.0)]
+    pub acceleration: f32,
+    #[default(20.0)]
+    pub gravity: f32,
+    #[default(0.4)]
+    pub step_height: f32,
+    #[default(0.3)]
+    pub snap_distance: f32,
+    #[default(std::f32::consts::FRAC_PI_4)]
+    pub max_slope: f32,
+    pub grounded: bool,
+    /// Moves with the desired velocity, ignoring gravity, ground and collisions.
+    pub noclip: bool,
+}
+
+impl CharacterController {
+    pub fn half_height(&self) -> f32 {
+        self.height * 0.5
+    }
+}
+
+/// Solid box taken from [`Aabb`] of the entity mesh, characters cannot walk through it.
+/// Boxes lower than [`CharacterController::step_height`] are stepped on instead.
+#[derive(Component, Default, Clone, Reflect, Debug)]
+pub struct Collider;
+
+/// Walkable terrain, characters are snapped onto it.
+#[derive(Component, Default, Clone, Reflect, Debug)]
+pub struct Ground;
+
+fn move_characters(
+    mut characters: Query<(Entity, &mut Transform, &mut CharacterController)>,
+    colliders: Query<(&GlobalTransform, &Aabb), With<Collider>>,
+    grounds: Query<(&GlobalTransform, &Aabb, &Mesh3d), With<Ground>>,
+    meshes: Res<Assets<Mesh>>,
+    mut hash: Local<SpatialHash<(Entity, f32, f32)>>,
+    time: Res<Time>,
+) {
+    let delta = time.delta_secs();
+    if delta == 0.0 {
+        return;
+    }
+
+    // snapshot of characters before they move, used for capsule vs capsule collisions
+    hash.clear(2.0);
+    for (entity, transform, controller) in characters.iter() {
+        hash.insert(
+            transform.translation,
+            (entity, controller.radius, controller.half_height()),
+        );
+    }
+
+    let colliders = colliders
+        .iter()
+        .map(|(transform, aabb)| world_box(transform, aabb))
+        .collect::<Vec<_>>();
+
+    for (entity, mut transform, mut controller) in characters.iter_mut() {
+        let controller = controller.as_mut();
+        let half_height = controller.half_height();
+
+        if controller.noclip {
+            controller.velocity = controller.desired_velocity;
+            controller.grounded = false;
+            transform.translation += controller.velocity * delta;
+            continue;
+        }
+
+        let horizontal = Vec3::new(controller.velocity.x, 0.0, controller.velocity.z);
+        let desired = Vec3::new(
+            controller.desired_velocity.x,
+            0.0,
+            controller.desired_velocity.z,
+        );
+        let horizontal = horizontal.move_towards(desired, controller.acceleration * delta);
+        let vertical = if controller.grounded {
+            0.0
+        } else {
+            controller.velocity.y - controller.gravity * delta
+        };
+        controller.velocity = Vec3::new(horizontal.x, vertical, horizontal.z);
+
+        let mut position = transform.translation + controller.velocity * delta;
+
+        // resting characters keep their ground, others probe it starting above the feet,
+        // so small steps up are found too
+        if controller.velocity != Vec3::ZERO || !controller.grounded {
+            let feet = position.y - half_height;
+            let probe_origin = Vec3::new(position.x, feet + controller.step_height, position.z);
+            let probe_length = controller.step_height + controller.snap_distance - vertical * delta;
+            let ground = cast_ground(Ray3d::new(probe_origin, Dir3::NEG_Y), &grounds, &meshes)
+                .filter(|hit| hit.distance <= probe_length)
+                .map(|hit| (hit.point, hit.normal));
+
+            controller.grounded = false;
+            if let Some((point, normal)) = ground {
+                let slope = normal.normalize_or(Vec3::Y).angle_between(Vec3::Y);
+                if slope > controller.max_slope && point.y > feet {
+                    // too steep to walk up, keep previous horizontal position
+                    position.x = transform.translation.x;
+                    position.z = transform.translation.z;
+                    controller.velocity.x = 0.0;
+                    controller.velocity.z = 0.0;
+                } else if vertical <= 0.0 {
+                    position.y = point.y + half_height;
+                    controller.grounded = true;
+                    controller.velocity.y = 0.0;
+                }
+            }
+        }
+
+        for (min, max) in colliders.iter() {
+            let feet = position.y - half_height;
+            if max.y <= feet || min.y >= position.y + half_height {
+                continue;
+            }
+
+            let closest = Vec3::new(
+                position.x.clamp(min.x, max.x),
+                0.0,
+                position.z.clamp(min.z, max.z),
+            );
+            let mut difference = Vec3::new(position.x, 0.0, position.z) - closest;
+            let distance = difference.length();
+            if distance >= controller.radius {
+                continue;
+            }
+
+            if max.y - feet <= controller.step_height && controller.velocity.y <= 0.0 {
+                position.y = max.y + half_height;
+                controller.grounded = true;
+                controller.velocity.y = 0.0;
+                continue;
+            }
+
+            let push = if distance > f32::EPSILON {
+                difference /= distance;
+                difference * (controller.radius - distance)
+            } else {
+                // center is inside the box, leave through the nearest side
+                let exits = [
+                    (Vec3::NEG_X, position.x - min.x),
+                    (Vec3::X, max.x - position.x),
+                    (Vec3::NEG_Z, position.z - min.z),
+                    (Vec3::Z, max.z - position.z),
+                ];
+                let (normal, depth) = exits
+                    .into_iter()
+                    .min_by(|a, b| a.1.total_cmp(&b.1))
+                    .unwrap();
+                difference = normal;
+                normal * (depth + controller.radius)
+            };
+
+            position += push;
+            controller.velocity -= difference * controller.velocity.dot(difference).min(0.0);
+        }
+
+        for (other_position, (other, other_radius, other_half_height)) in
+            hash.nearby(position, controller.radius * 2.0)
+        {
+            if *other == entity
+                || (position.y - other_position.y).abs() >= half_height + other_half_height
+            {
+                continue;
+            }
+
+            let mut difference = position - *other_position;
+            difference.y = 0.0;
+            let distance = difference.length();
+            let overlap = controller.radius + other_radius - distance;
+            if overlap > 0.0 && distance > f32::EPSILON {
+                // other character is pushed by the same amount when it is processed
+                position += difference / distance * overlap * 0.5;
+            }
+        }
+
+        transform.translation = position;
+    }
+}
+
+/// Nearest hit on [`Ground`] meshes. Other meshes are not tested at all, so the cost
+/// does not grow with the number of characters and props in the scene.
+fn cast_ground(
+    ray: Ray3d,
+    grounds: &Query<(&GlobalTransform, &Aabb, &Mesh3d), With<Ground>>,
+    meshes: &Assets<Mesh>,
+) -> Option<RayMeshHit> {
+    grounds
+        .iter()
+        .filter_map(|(transform, aabb, mesh)| {
+            let transform = transform.compute_matrix();
+            ray_aabb_intersection_3d(
+                ray,
+                &Aabb3d::new(aabb.center, aabb.half_extents),
+                &transform,
+            )?;
+            let mesh = meshes.get(&mesh.0)?;
+            if mesh.primitive_topology() != PrimitiveTopology::TriangleList {
+                return None;
+            }
+
+            let positions = mesh.attribute(Mesh::ATTRIBUTE_POSITION)?.as_float3()?;
+            let normals = mesh
+                .attribute(Mesh::ATTRIBUTE_NORMAL)
+                .and_then(|normals| normals.as_float3());
+            match mesh.indices() {
+                Some(Indices::U16(indices)) => ray_mesh_intersection(
+                    ray,
+                    &transform,
+                    positions,
+                    normals,
+                    Some(indices),
+                    Backfaces::Cull,
+                ),
+                Some(Indices::U32(indices)) => ray_mesh_intersection(
+                    ray,
+                    &transform,
+                    positions,
+                    normals,
+                    Some(indices),
+                    Backfaces::Cull,
+                ),
+                None => ray_mesh_intersection::<usize>(
+                    ray,
+                    &transform,
+                    positions,
+                    normals,
+                    None,
+                    Backfaces::Cull,
+                ),
+            }
+        })
+        .min_by(|a, b| a.distance.total_cmp(&b.distance))
+}
+
+fn world_box(transform: &GlobalTransform, aabb: &Aabb) -> (Vec3, Vec3) {
+    let (min, max) = (Vec3::from(aabb.min()), Vec3::from(aabb.max()));
+    let corners = [
+        Vec3::new(min.x, min.y, min.z),
+        Vec3::new(min.x, min.y, max.z),
+        Vec3::new(min.x, max.y, min.z),
+        Vec3::new(min.x, max.y, max.z),
+        Vec3::new(max.x, min.y, min.z),
+        Vec3::new(max.x, min.y, max.z),
+        Vec3::new(max.x, max.y, min.z),
+        Vec3::new(max.x, max.y, max.z),
+    ]
+    .map(|c| transform.transform_point(c));
+
+    (
+        corners.into_iter().reduce(Vec3::min).unwrap(),
+        corners.into_iter().reduce(Vec3::max).unwrap(),
+    )
+}
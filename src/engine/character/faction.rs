@@ -0,0 +1,161 @@
+use std::marker::PhantomData;
+
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use bevy::reflect::Reflectable;
+use bevy::utils::HashMap;
+use derive_more::derive::Display;
+
+use super::AttackEvent;
+use crate::engine::prototype::PrototypeId;
+
+pub const MIN_REPUTATION: i16 = -100;
+pub const MAX_REPUTATION: i16 = 100;
+pub const HOSTILE_REPUTATION: i16 = -50;
+pub const FRIENDLY_REPUTATION: i16 = 50;
+pub const ATTACK_REPUTATION_PENALTY: i16 = 25;
+
+pub struct FactionPlugin<FactionId: PrototypeId> {
+    _faction_id: PhantomData<FactionId>,
+}
+
+impl<FactionId: PrototypeId> Default for FactionPlugin<FactionId> {
+    fn default() -> Self {
+        Self {
+            _faction_id: default(),
+        }
+    }
+}
+
+impl<FactionId: PrototypeId + Reflectable + FromReflect> Plugin for FactionPlugin<FactionId> {
+    fn build(&self, app: &mut App) {
+        app.register_type::<Faction<FactionId>>();
+        app.add_systems(Update, update_reputation_on_attack::<FactionId>);
+    }
+}
+
+#[derive(Component, Clone, Copy, Reflect, Debug)]
+#[reflect(Component)]
+pub struct Faction<T: PrototypeId>(pub T);
+
+#[derive(Clone, Copy, PartialEq, Eq, Display, Debug)]
+pub enum Relationship {
+    Hostile,
+    Neutral,
+    Friendly,
+}
+
+impl Relationship {
+    pub fn from_reputation(reputation: i16) -> Self {
+        if reputation <= HOSTILE_REPUTATION {
+            Self::Hostile
+        } else if reputation >= FRIENDLY_REPUTATION {
+            Self::Friendly
+        } else {
+            Self::Neutral
+        }
+    }
+}
+
+/// Reputation of every faction towards other factions.
+/// Reputation is directional, so `(a, b)` describes how faction `a` sees faction `b`.
+/// Pairs that were never set are neutral, a faction is always friendly towards itself.
+#[derive(Resource)]
+pub struct FactionRelations<T: PrototypeId>(HashMap<(T, T), i16>);
+
+impl<T: PrototypeId> Default for FactionRelations<T> {
+    fn default() -> Self {
+        Self(HashMap::new())
+    }
+}
+
+impl<T: PrototypeId> FactionRelations<T> {
+    pub fn reputation(&self, faction: T, towards: T) -> i16 {
+        if faction == towards {
+            return MAX_REPUTATION;
+        }
+
+        self.0.get(&(faction, towards)).copied().unwrap_or(0)
+    }
+
+    pub fn set_reputation(&mut self, faction: T, towards: T, reputation: i16) {
+        if faction == towards {
+            return;
+        }
+
+        self.0.insert(
+            (faction, towards),
+            reputation.clamp(MIN_REPUTATION, MAX_REPUTATION),
+        );
+    }
+
+    pub fn set_mutual_reputation(&mut self, a: T, b: T, reputation: i16) {
+        self.set_reputation(a, b, reputation);
+        self.set_reputation(b, a, reputation);
+    }
+
+    pub fn change_reputation(&mut self, faction: T, towards: T, change: i16) {
+        let reputation = self.reputation(faction, towards).saturating_add(change);
+        self.set_reputation(faction, towards, reputation);
+    }
+
+    pub fn relationship(&self, faction: T, towards: T) -> Relationship {
+        Relationship::from_reputation(self.reputation(faction, towards))
+    }
+
+    /// Factions that have reputation towards any other faction or are seen by one.
+    pub fn factions(&self) -> Vec<T> {
+        let mut factions = Vec::new();
+        for faction in self.0.keys().flat_map(|(a, b)| [*a, *b]) {
+            if !factions.contains(&faction) {
+                factions.push(faction);
+            }
+        }
+        factions
+    }
+
+    pub fn is_hostile(&self, faction: T, towards: T) -> bool {
+        self.relationship(faction, towards) == Relationship::Hostile
+    }
+}
+
+/// Entity level access to [`FactionRelations`], entities without [`Faction`] have no relationship.
+#[derive(SystemParam)]
+pub struct FactionQuery<'w, 's, T: PrototypeId> {
+    relations: ResMut<'w, FactionRelations<T>>,
+    factions: Query<'w, 's, &'static Faction<T>>,
+}
+
+impl<T: PrototypeId> FactionQuery<'_, '_, T> {
+    pub fn faction(&self, entity: Entity) -> Option<T> {
+        self.factions.get(entity).ok().map(|f| f.0)
+    }
+
+    pub fn is_hostile(&self, entity: Entity, towards: Entity) -> bool {
+        self.faction(entity)
+            .zip(self.faction(towards))
+            .is_some_and(|(faction, towards)| self.relations.is_hostile(faction, towards))
+    }
+
+    pub fn change_reputation(&mut self, entity: Entity, towards: Entity, change: i16) {
+        if let (Some(faction), Some(towards)) = (self.faction(entity), self.faction(towards)) {
+            self.relations.change_reputation(faction, towards, change);
+        }
+    }
+}
+
+fn update_reputation_on_attack<T: PrototypeId>(
+    mut attack_events: EventReader<AttackEvent>,
+    mut factions: FactionQuery<T>,
+) {
+    for attack in attack_events.read() {
+        let Some(attacker) = attack.attacker else {
+            continue;
+        };
+
+        // members of the same faction are friendly, so their attacks change nothing
+        if !factions.is_hostile(attack.target, attacker) {
+            factions.change_reputation(attack.target, attacker, -ATTACK_REPUTATION_PENALTY);
+        }
+    }
+}
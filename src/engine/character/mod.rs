@@ -1,12 +1,21 @@
+pub mod avoidance;
+pub mod controller;
+pub mod faction;
 pub mod npc;
 pub mod player;
 
+use avoidance::{Avoidance, AvoidancePlugin};
+use bevy::ecs::system::SystemParam;
 use bevy::prelude::*;
+use controller::{CharacterController, CharacterControllerPlugin};
 use npc::NpcPlugin;
-use player::PlayerPlugin;
+use player::{Player, PlayerPlugin};
 use smart_default::SmartDefault;
 
 use super::item::storage::ItemStorage;
+use super::navigation::NavigationSet;
+use super::simulation::Interpolated;
+use super::spatial::SpatialIndexed;
 
 pub struct CharacterPlugin;
 
@@ -15,15 +24,52 @@ impl Plugin for CharacterPlugin {
         app.register_type::<Character>();
         app.register_type::<Health>();
         app.register_type::<Speed>();
-        app.add_plugins((PlayerPlugin, NpcPlugin));
+        app.register_type::<Invulnerable>();
+        app.add_event::<AttackEvent>();
+        app.add_event::<DeathEvent>();
+        app.add_systems(Update, despawn_dead_characters);
+        app.configure_sets(
+            FixedUpdate,
+            (
+                CharacterSet::Movement.after(NavigationSet),
+                CharacterSet::Avoidance,
+                CharacterSet::Physics,
+            )
+                .chain(),
+        );
+        app.add_plugins((
+            PlayerPlugin,
+            NpcPlugin,
+            AvoidancePlugin,
+            CharacterControllerPlugin,
+        ));
     }
 }
 
+/// Steps of character simulation, they run in [`FixedUpdate`].
+#[derive(SystemSet, Clone, PartialEq, Eq, Hash, Debug)]
+pub enum CharacterSet {
+    Movement,
+    Avoidance,
+    Physics,
+}
+
 #[derive(Component, Default, Reflect, Debug)]
-#[require(Transform, Name(|| Name::new("Character")), Health, Speed, ItemStorage)]
+#[require(
+    Transform,
+    Name(|| Name::new("Character")),
+    Health,
+    Speed,
+    Avoidance,
+    CharacterController,
+    ItemStorage,
+    Interpolated,
+    SpatialIndexed
+)]
 pub struct Character;
 
 #[derive(Component, SmartDefault, Reflect, Debug)]
+#[reflect(Component)]
 pub struct Health {
     #[default(100)]
     pub current: u16,
@@ -31,5 +77,106 @@ pub struct Health {
     pub max: u16,
 }
 
+impl Health {
+    pub fn damage(&mut self, amount: u16) {
+        self.current = self.current.saturating_sub(amount);
+    }
+
+    pub fn heal(&mut self, amount: u16) {
+        self.current = self.current.saturating_add(amount).min(self.max);
+    }
+
+    pub fn is_dead(&self) -> bool {
+        self.current == 0
+    }
+}
+
+/// Character that does not take damage.
+#[derive(Component, Default, Reflect, Debug)]
+#[reflect(Component)]
+pub struct Invulnerable;
+
 #[derive(Component, SmartDefault, Reflect, Debug)]
+#[reflect(Component)]
 pub struct Speed(#[default(5.0)] pub f32);
+
+/// Sent by [`Damage`] for every damage dealt.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct AttackEvent {
+    /// Not set for damage without a source, like the one from console commands.
+    pub attacker: Option<Entity>,
+    pub target: Entity,
+}
+
+/// Sent by [`Damage`] when health of the character drops to zero.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct DeathEvent {
+    pub entity: Entity,
+}
+
+/// The only way health should be lowered, so every damage is reported with [`AttackEvent`]
+/// and every death with [`DeathEvent`].
+#[derive(SystemParam)]
+pub struct Damage<'w, 's> {
+    healths: Query<'w, 's, (&'static mut Health, Has<Invulnerable>)>,
+    attacks: EventWriter<'w, AttackEvent>,
+    deaths: EventWriter<'w, DeathEvent>,
+}
+
+impl Damage<'_, '_> {
+    /// Damages the target unless it is invulnerable, returns its remaining health.
+    pub fn deal(
+        &mut self,
+        attacker: Option<Entity>,
+        target: Entity,
+        amount: u16,
+    ) -> Result<&Health, String> {
+        match self.healths.get(target) {
+            Ok((_, true)) => Err(format!("{} is invulnerable", target)),
+            Ok((_, false)) => self.apply(attacker, target, amount),
+            Err(_) => Err(format!("{} has no health", target)),
+        }
+    }
+
+    /// Takes all health of the target, even an invulnerable one.
+    pub fn kill(&mut self, attacker: Option<Entity>, target: Entity) -> Result<&Health, String> {
+        self.apply(attacker, target, u16::MAX)
+    }
+
+    fn apply(
+        &mut self,
+        attacker: Option<Entity>,
+        target: Entity,
+        amount: u16,
+    ) -> Result<&Health, String> {
+        let Ok((mut health, _)) = self.healths.get_mut(target) else {
+            return Err(format!("{} has no health", target));
+        };
+        if health.is_dead() {
+            return Err(format!("{} is already dead", target));
+        }
+
+        health.damage(amount);
+        self.attacks.send(AttackEvent { attacker, target });
+        if health.is_dead() {
+            self.deaths.send(DeathEvent { entity: target });
+        }
+        Ok(self.healths.get(target).expect("target was damaged").0)
+    }
+}
+
+/// Player is respawned instead, by [`PlayerPlugin`].
+fn despawn_dead_characters(
+    mut commands: Commands,
+    mut deaths: EventReader<DeathEvent>,
+    players: Query<(), With<Player>>,
+) {
+    for death in deaths.read() {
+        if players.contains(death.entity) {
+            continue;
+        }
+        if let Some(entity) = commands.get_entity(death.entity) {
+            entity.despawn_recursive();
+        }
+    }
+}
@@ -4,30 +4,44 @@ use bevy::prelude::*;
 use rand::Rng;
 use smart_default::SmartDefault;
 
-use super::{Character, Speed};
+use super::controller::CharacterController;
+use super::{Character, CharacterSet, Speed};
+use crate::engine::navigation::{NavPath, PathStatus};
+use crate::engine::random::GameRng;
 
 pub struct NpcPlugin;
 
 impl Plugin for NpcPlugin {
     fn build(&self, app: &mut App) {
         app.register_type::<Npc>();
-        app.add_systems(Update, move_npcs);
+        app.add_systems(FixedUpdate, move_npcs.in_set(CharacterSet::Movement));
     }
 }
 
 #[derive(Component, SmartDefault, Clone, Reflect, Debug)]
-#[require(Name(|| Name::new("NPC")), Character)]
+#[require(Name(|| Name::new("NPC")), Character, NavPath)]
 pub enum Npc {
     #[default]
     Idle(#[default(Timer::new(Duration::from_secs(1), TimerMode::Once))] Timer),
     Moving(Vec3),
 }
 
-fn move_npcs(mut npcs: Populated<(&mut Transform, &mut Npc, &Speed)>, time: Res<Time>) {
+fn move_npcs(
+    mut npcs: Populated<(
+        &Transform,
+        &mut CharacterController,
+        &mut Npc,
+        &mut NavPath,
+        &Speed,
+    )>,
+    mut rng: ResMut<GameRng>,
+    time: Res<Time>,
+) {
     // temporary ai implementation: move to random places xd
 
-    let mut rng = rand::thread_rng();
-    for (mut transform, mut npc, speed) in npcs.iter_mut() {
+    for (transform, mut controller, mut npc, mut path, speed) in npcs.iter_mut() {
+        controller.desired_velocity = Vec3::ZERO;
+
         match npc.as_mut() {
             Npc::Idle(timer) => {
                 timer.tick(time.delta());
@@ -41,19 +55,26 @@ fn move_npcs(mut npcs: Populated<(&mut Transform, &mut Npc, &Speed)>, time: Res<
                     let distance = rng.gen_range(1.0..25.0);
 
                     let target = transform.translation + direction * distance;
+                    path.request(target);
                     *npc = Npc::Moving(target);
                 }
             }
-            Npc::Moving(target) => {
-                let direction = (*target - transform.translation).normalize_or_zero();
-                transform.translation += direction * speed.0 * time.delta_secs();
+            Npc::Moving(_) => {
+                if path.status == PathStatus::Pending {
+                    continue;
+                }
 
-                if transform.translation.distance(*target) < 0.1 {
+                let Some(velocity) = path.follow(transform.translation, speed.0, time.delta_secs())
+                else {
+                    path.clear();
                     *npc = Npc::Idle(Timer::new(
                         Duration::from_secs_f32(rng.gen_range(0.1..3.0)),
                         TimerMode::Once,
-                    ))
-                }
+                    ));
+                    continue;
+                };
+
+                controller.desired_velocity = velocity;
             }
         }
     }
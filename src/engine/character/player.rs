@@ -1,9 +1,15 @@
+use bevy::picking::focus::HoverMap;
+use bevy::picking::pointer::{PointerButton, PointerId};
 use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
 
-use super::{Character, Speed};
+use super::controller::{CharacterController, Ground};
+use super::{Character, CharacterSet, DeathEvent, Health, Speed};
+use crate::engine::camera::GameCamera;
 use crate::engine::input::GameplayInput;
-use crate::engine::item::Item;
-use crate::engine::item::storage::InsertItemCommand;
+use crate::engine::input::context::{InputContext, InputContexts};
+use crate::engine::interaction::{Interactables, InteractionEvent};
+use crate::engine::navigation::{NavGrid, NavPath, PathStatus};
 
 pub struct PlayerPlugin;
 
@@ -12,53 +18,317 @@ impl Plugin for PlayerPlugin {
         app.add_plugins(MeshPickingPlugin);
 
         app.register_type::<Player>();
-        app.add_systems(Update, (move_player, pickup_items));
+        app.register_type::<PlayerOrder>();
+        app.register_type::<PlayerSpawn>();
+        app.init_resource::<PointerTarget>();
+        app.add_systems(
+            Update,
+            (
+                update_pointer_target,
+                click_pointer_target,
+                draw_pointer_target,
+            )
+                .chain(),
+        );
+        app.add_systems(Update, (record_player_spawn, respawn_player));
+        app.add_systems(
+            FixedUpdate,
+            (give_player_order, move_player, follow_player_order)
+                .chain()
+                .in_set(CharacterSet::Movement),
+        );
     }
 }
 
+/// Clicking the ground this close to an interactable targets the interactable instead,
+/// so small objects are easy to click.
+const CURSOR_SNAP_RADIUS: f32 = 0.75;
+
 #[derive(Component, Default, Clone, Reflect, Debug)]
-#[require(Name(|| Name::new("Player")), Character)]
+#[require(Name(|| Name::new("Player")), Character, PlayerOrder, NavPath)]
 pub struct Player;
 
+/// Where the player was spawned, it is brought back there after death.
+#[derive(Component, Clone, Copy, Reflect, Debug)]
+#[reflect(Component)]
+pub struct PlayerSpawn(pub Vec3);
+
+/// Order given by clicking, player walks along a path until it is done.
+/// Keyboard or gamepad movement cancels it.
+#[derive(Component, Default, Clone, Reflect, Debug)]
+pub enum PlayerOrder {
+    #[default]
+    None,
+    MoveTo(Vec3),
+    Interact(Entity),
+}
+
+/// What is under the mouse cursor and what clicking it will do.
+#[derive(Resource, Default, Clone, Copy, PartialEq, Serialize, Deserialize, Reflect, Debug)]
+pub enum PointerTarget {
+    #[default]
+    None,
+    Ground(Vec3),
+    Interactable(Entity),
+}
+
+fn update_pointer_target(
+    mut target: ResMut<PointerTarget>,
+    hover_map: Res<HoverMap>,
+    contexts: Res<InputContexts>,
+    interactables: Interactables,
+    grounds: Query<(), With<Ground>>,
+) {
+    // only the nearest hit counts, so obstacles and characters hide the ground behind them
+    let hit = hover_map
+        .get(&PointerId::Mouse)
+        .and_then(|hits| hits.iter().min_by(|a, b| a.1.depth.total_cmp(&b.1.depth)));
+
+    let new_target = match hit {
+        _ if contexts.active() != InputContext::Gameplay => PointerTarget::None,
+        Some((entity, _)) if interactables.get(*entity).is_some() => {
+            PointerTarget::Interactable(*entity)
+        }
+        Some((entity, hit)) if grounds.contains(*entity) => match hit.position {
+            Some(point) => interactables
+                .nearest(point, CURSOR_SNAP_RADIUS)
+                .map_or(PointerTarget::Ground(point), PointerTarget::Interactable),
+            None => PointerTarget::None,
+        },
+        _ => PointerTarget::None,
+    };
+
+    if *target != new_target {
+        *target = new_target;
+    }
+}
+
+/// Clicks go through [`GameplayInput`], so orders are applied in fixed steps and recorded.
+fn click_pointer_target(
+    mut click_events: EventReader<Pointer<Down>>,
+    target: Res<PointerTarget>,
+    mut input: ResMut<GameplayInput>,
+) {
+    let clicked = click_events
+        .read()
+        .any(|click| click.button == PointerButton::Primary);
+    if clicked && *target != PointerTarget::None {
+        // cleared by the next fixed step, like other pressed actions
+        input.order = Some(*target);
+    }
+}
+
+fn give_player_order(
+    input: Res<GameplayInput>,
+    player: Single<(&mut PlayerOrder, &mut NavPath), With<Player>>,
+) {
+    let Some(target) = input.order else {
+        return;
+    };
+
+    let (mut order, mut path) = player.into_inner();
+    match target {
+        PointerTarget::None => {}
+        PointerTarget::Ground(point) => {
+            path.request(point);
+            *order = PlayerOrder::MoveTo(point);
+        }
+        PointerTarget::Interactable(target) => {
+            // path is requested once the target position is known
+            path.clear();
+            *order = PlayerOrder::Interact(target);
+        }
+    }
+}
+
 fn move_player(
-    mut player: Single<(&mut Transform, &Speed), With<Player>>,
+    player: Single<(&mut CharacterController, &mut PlayerOrder, &Speed), With<Player>>,
+    camera: Option<Single<&GameCamera>>,
     input: Res<GameplayInput>,
-    time: Res<Time>,
 ) {
-    if input.movement == Vec2::ZERO {
+    let (mut controller, mut order, speed) = player.into_inner();
+
+    // free-fly camera uses the same input, player stands still meanwhile
+    if camera
+        .as_ref()
+        .is_some_and(|camera| camera.captures_movement())
+    {
+        controller.desired_velocity = Vec3::ZERO;
         return;
     }
 
-    let direction = Vec3::new(input.movement.x, 0.0, -input.movement.y);
-    let speed = {
-        let speed = if input.sprint {
-            player.1.0 * 2.0
-        } else {
-            player.1.0
-        };
-        speed.clamp(0.0, 100.0)
-    };
-    player.0.translation += direction * speed * time.delta_secs();
+    if input.movement != Vec2::ZERO {
+        *order = PlayerOrder::None;
+    }
+
+    // forward is where the camera looks, so movement keeps working after rotating it
+    let direction =
+        Quat::from_rotation_y(input.view_yaw) * Vec3::new(input.movement.x, 0.0, -input.movement.y);
+    controller.desired_velocity = direction * player_speed(speed, &input);
 }
 
-fn pickup_items(
-    mut commands: Commands,
-    mut click_events: EventReader<Pointer<Down>>,
-    items: Query<&GlobalTransform, (With<Item>, With<Transform>)>,
-    player: Single<(Entity, &GlobalTransform), With<Player>>,
+fn follow_player_order(
+    mut events: EventWriter<InteractionEvent>,
+    player: Single<
+        (
+            Entity,
+            &Transform,
+            &mut CharacterController,
+            &mut PlayerOrder,
+            &mut NavPath,
+            &Speed,
+        ),
+        With<Player>,
+    >,
+    interactables: Interactables,
+    transforms: Query<&GlobalTransform>,
+    grid: Res<NavGrid>,
+    input: Res<GameplayInput>,
+    time: Res<Time>,
 ) {
-    for click in click_events.read() {
-        if let Ok(item_transform) = items.get(click.target) {
-            if item_transform
-                .translation()
-                .distance(player.1.translation())
-                <= 5.0
-            {
-                commands.queue(InsertItemCommand {
-                    storage: player.0,
-                    item: click.target,
+    let (entity, transform, mut controller, mut order, mut path, speed) = player.into_inner();
+
+    match *order {
+        PlayerOrder::None => {
+            if path.status != PathStatus::Idle {
+                path.clear();
+            }
+            return;
+        }
+        PlayerOrder::MoveTo(_) => {}
+        PlayerOrder::Interact(target) => {
+            let (Some(interactable), Ok(target_transform)) =
+                (interactables.get(target), transforms.get(target))
+            else {
+                // target was picked up by someone else or despawned
+                *order = PlayerOrder::None;
+                return;
+            };
+
+            if interactables.in_range(target, transform.translation) {
+                events.send(InteractionEvent {
+                    actor: entity,
+                    target,
+                    kind: interactable.kind,
                 });
+                path.clear();
+                *order = PlayerOrder::None;
+                return;
+            }
+
+            if path.status == PathStatus::Idle {
+                // walk to the near edge of the range, target itself can be an obstacle,
+                // so the closest walkable cell still in range is used
+                let target_position = target_transform.translation();
+                let mut direction = transform.translation - target_position;
+                direction.y = 0.0;
+                let range = interactable.range * 0.8;
+                let approach = target_position + direction.normalize_or_zero() * range;
+                match grid.nearest_walkable(target_position, range, approach) {
+                    Some(point) => path.request(point),
+                    None => {
+                        *order = PlayerOrder::None;
+                        return;
+                    }
+                }
+            }
+        }
+    }
+
+    match path.status {
+        PathStatus::Pending => {}
+        PathStatus::Failed | PathStatus::Idle => *order = PlayerOrder::None,
+        PathStatus::Ready => {
+            match path.follow(
+                transform.translation,
+                player_speed(speed, &input),
+                time.delta_secs(),
+            ) {
+                Some(velocity) => controller.desired_velocity = velocity,
+                None => {
+                    // destination reached, or target is still out of range at the path end
+                    path.clear();
+                    *order = PlayerOrder::None;
+                }
             }
         }
     }
 }
+
+fn record_player_spawn(
+    mut commands: Commands,
+    players: Query<(Entity, &Transform), (With<Player>, Without<PlayerSpawn>)>,
+) {
+    for (entity, transform) in players.iter() {
+        commands
+            .entity(entity)
+            .insert(PlayerSpawn(transform.translation));
+    }
+}
+
+fn respawn_player(
+    mut deaths: EventReader<DeathEvent>,
+    mut players: Query<
+        (
+            &mut Health,
+            &mut Transform,
+            &mut CharacterController,
+            &mut PlayerOrder,
+            &mut NavPath,
+            &PlayerSpawn,
+        ),
+        With<Player>,
+    >,
+) {
+    for death in deaths.read() {
+        let Ok((mut health, mut transform, mut controller, mut order, mut path, spawn)) =
+            players.get_mut(death.entity)
+        else {
+            continue;
+        };
+
+        info!("Player {} died and has been respawned", death.entity);
+        health.current = health.max;
+        transform.translation = spawn.0;
+        // movement from before the death would carry over
+        controller.velocity = Vec3::ZERO;
+        controller.grounded = false;
+        path.clear();
+        *order = PlayerOrder::None;
+    }
+}
+
+fn player_speed(speed: &Speed, input: &GameplayInput) -> f32 {
+    let speed = if input.sprint { speed.0 * 2.0 } else { speed.0 };
+    speed.clamp(0.0, 100.0)
+}
+
+fn draw_pointer_target(
+    mut gizmos: Gizmos,
+    target: Res<PointerTarget>,
+    transforms: Query<&GlobalTransform>,
+) {
+    let (position, radius, color) = match *target {
+        PointerTarget::None => return,
+        PointerTarget::Ground(point) => (point, 0.3, Color::WHITE),
+        PointerTarget::Interactable(target) => {
+            let Ok(transform) = transforms.get(target) else {
+                return;
+            };
+            (
+                transform.translation(),
+                0.8,
+                Color::linear_rgb(1.0, 0.8, 0.2),
+            )
+        }
+    };
+
+    gizmos.circle(
+        Isometry3d::new(
+            position + Vec3::Y * 0.05,
+            Quat::from_rotation_x(std::f32::consts::FRAC_PI_2),
+        ),
+        radius,
+        color,
+    );
+}
@@ -0,0 +1,522 @@
+pub mod settings;
+
+use std::ffi::OsString;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use bevy::prelude::*;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use serde_json::{Map, Value};
+
+/// Part of the settings file, kept in a resource of the same type. Engine settings are sections
+/// too, games can add their own with [`ConfigAppExt::add_config_section`].
+pub trait ConfigSection: Resource + Serialize + DeserializeOwned + Default {
+    /// Key of the section in the settings file, environment variables and `--set` option.
+    const NAME: &'static str;
+}
+
+/// Settings layered as defaults, then settings file, then environment variables
+/// (`ANDROMEDA_GRAPHICS__QUALITY=Low`), then command line (`--set graphics.quality=Low`).
+/// Sections changed at runtime, for example in options menu, are saved back to the file,
+/// but values coming from environment or command line are not, unless they were changed.
+#[derive(Resource, Debug)]
+pub struct Config {
+    file: PathBuf,
+    /// Content of the settings file, including sections that are not used by this run.
+    stored: Map<String, Value>,
+    /// Environment and command line values in the order they are applied.
+    overrides: Vec<ConfigOverride>,
+    /// Set when the settings file cannot be parsed, so it is not overwritten with defaults.
+    read_only: bool,
+}
+
+#[derive(Clone, Debug)]
+struct ConfigOverride {
+    section: String,
+    path: Vec<String>,
+    value: Value,
+}
+
+impl Config {
+    pub fn new(file: &Path) -> Self {
+        Self {
+            file: file.to_path_buf(),
+            stored: Map::new(),
+            overrides: Vec::new(),
+            read_only: false,
+        }
+    }
+
+    /// Reads the settings file if it exists. If it cannot be read, defaults are used
+    /// and the file is left untouched.
+    pub fn read_file(&mut self) -> Result<(), String> {
+        if !self.file.exists() {
+            return Ok(());
+        }
+
+        let stored = fs::read_to_string(&self.file)
+            .map_err(|e| format!("Cannot read '{}': {}", self.file.display(), e))
+            .and_then(|content| {
+                serde_json::from_str(&content)
+                    .map_err(|e| format!("Cannot parse '{}': {}", self.file.display(), e))
+            });
+        match stored {
+            Ok(stored) => {
+                self.stored = stored;
+                Ok(())
+            }
+            Err(error) => {
+                self.read_only = true;
+                Err(error)
+            }
+        }
+    }
+
+    /// Reads environment variables starting with `env_prefix`, their names are section
+    /// and field path separated with `__`. Variables that are not valid UTF-8 are skipped.
+    pub fn read_env(&mut self, env_prefix: &str) {
+        self.read_variables(env_prefix, std::env::vars_os());
+    }
+
+    fn read_variables(
+        &mut self,
+        env_prefix: &str,
+        variables: impl IntoIterator<Item = (OsString, OsString)>,
+    ) {
+        let mut variables = variables
+            .into_iter()
+            .filter_map(|(name, value)| {
+                let name = name.into_string().ok()?;
+                let value = value.into_string().ok()?;
+                Some((name.strip_prefix(env_prefix)?.to_string(), value))
+            })
+            .collect::<Vec<_>>();
+        // deterministic order when nested and whole values of the same field are both set
+        variables.sort();
+        for (name, value) in variables {
+            let mut path = name.split("__");
+            let Some(section) = path.next().filter(|s| !s.is_empty()) else {
+                continue;
+            };
+            self.set_override(section, path, parse_value(&value));
+        }
+    }
+
+    /// Parses `section.field=value` assignment of `--set` option.
+    pub fn set_from_str(&mut self, assignment: &str) -> Result<(), String> {
+        let (path, value) = assignment
+            .split_once('=')
+            .ok_or_else(|| format!("Expected 'section.field=value', got '{}'", assignment))?;
+        let mut path = path.trim().split('.');
+        let section = path
+            .next()
+            .filter(|s| !s.is_empty())
+            .ok_or_else(|| format!("Missing section in '{}'", assignment))?;
+
+        self.set_override(section, path, parse_value(value.trim()));
+        Ok(())
+    }
+
+    /// Overrides a field of the section, applied over the settings file and earlier overrides.
+    /// Empty path replaces the whole section.
+    pub fn set_override<'a>(
+        &mut self,
+        section: &str,
+        path: impl IntoIterator<Item = &'a str>,
+        value: impl Serialize,
+    ) {
+        let value = serde_json::to_value(value).expect("Override should be serializable");
+        self.overrides.push(ConfigOverride {
+            section: section.to_string(),
+            path: path.into_iter().map(str::to_string).collect(),
+            value,
+        });
+    }
+
+    /// Section with all layers applied, fields missing in the file get default values.
+    pub fn section<T: ConfigSection>(&self) -> Result<T, String> {
+        let mut value = self.layered(T::NAME, &T::default())?;
+        for o in self
+            .overrides
+            .iter()
+            .filter(|o| o.section.eq_ignore_ascii_case(T::NAME))
+        {
+            *path_entry(&mut value, &o.path) = o.value.clone();
+        }
+
+        serde_json::from_value(value)
+            .map_err(|e| format!("Invalid '{}' settings section: {}", T::NAME, e))
+    }
+
+    /// Updates the section in the file content, override values that were not changed
+    /// are replaced with the ones from the file.
+    pub fn store<T: ConfigSection>(&mut self, section: &T) -> Result<(), String> {
+        let mut value = serde_json::to_value(section)
+            .map_err(|e| format!("Cannot serialize '{}' settings section: {}", T::NAME, e))?;
+        let defaults = self.layered(T::NAME, &T::default())?;
+
+        for o in self
+            .overrides
+            .iter()
+            .filter(|o| o.section.eq_ignore_ascii_case(T::NAME))
+        {
+            if path_get(&value, &o.path) != Some(&o.value) {
+                continue;
+            }
+            match path_get(&defaults, &o.path) {
+                Some(stored) => *path_entry(&mut value, &o.path) = stored.clone(),
+                None => path_remove(&mut value, &o.path),
+            }
+        }
+
+        self.stored.insert(T::NAME.to_string(), value);
+        Ok(())
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        if self.read_only {
+            return Err(format!(
+                "Settings are not saved, '{}' could not be loaded",
+                self.file.display()
+            ));
+        }
+
+        let content = serde_json::to_string_pretty(&self.stored)
+            .map_err(|e| format!("Cannot serialize settings: {}", e))?;
+        fs::write(&self.file, content)
+            .map_err(|e| format!("Cannot write '{}': {}", self.file.display(), e))
+    }
+
+    /// Defaults of the section with the file content merged over them.
+    fn layered(&self, name: &str, defaults: &impl Serialize) -> Result<Value, String> {
+        let mut value = serde_json::to_value(defaults)
+            .map_err(|e| format!("Cannot serialize '{}' settings section: {}", name, e))?;
+        if let Some(stored) = self.stored.get(name) {
+            merge(&mut value, stored);
+        }
+        Ok(value)
+    }
+}
+
+pub trait ConfigAppExt {
+    /// Inserts the section loaded from [`Config`], unless it is already inserted,
+    /// and saves it back whenever it changes. Invalid section is replaced with defaults.
+    fn add_config_section<T: ConfigSection>(&mut self) -> &mut Self;
+}
+
+impl ConfigAppExt for App {
+    fn add_config_section<T: ConfigSection>(&mut self) -> &mut Self {
+        if !self.world().contains_resource::<T>() {
+            let section = self
+                .world()
+                .resource::<Config>()
+                .section::<T>()
+                .unwrap_or_else(|error| {
+                    error!("{}, defaults will be used", error);
+                    T::default()
+                });
+            self.insert_resource(section);
+        }
+
+        self.add_systems(
+            Last,
+            save_config_section::<T>.run_if(resource_changed::<T>.and(not(resource_added::<T>))),
+        )
+    }
+}
+
+fn save_config_section<T: ConfigSection>(section: Res<T>, mut config: ResMut<Config>) {
+    if let Err(error) = config.store(&*section).and_then(|_| config.save()) {
+        error!("{}", error);
+    }
+}
+
+/// Values that are not valid JSON are used as strings, so `Low` or `KeyW` need no quotes.
+fn parse_value(value: &str) -> Value {
+    serde_json::from_str(value).unwrap_or_else(|_| Value::String(value.to_string()))
+}
+
+/// Keys are matched case-insensitively, environment variables are usually upper case.
+fn find_key<'a>(map: &'a Map<String, Value>, key: &str) -> Option<&'a String> {
+    map.keys().find(|k| k.eq_ignore_ascii_case(key))
+}
+
+fn merge(value: &mut Value, over: &Value) {
+    match (value, over) {
+        (Value::Object(map), Value::Object(over)) => {
+            for (key, over) in over {
+                match map.get_mut(key) {
+                    Some(value) => merge(value, over),
+                    None => {
+                        map.insert(key.clone(), over.clone());
+                    }
+                }
+            }
+        }
+        (value, over) => *value = over.clone(),
+    }
+}
+
+fn path_get<'a>(value: &'a Value, path: &[String]) -> Option<&'a Value> {
+    path.iter().try_fold(value, |value, key| {
+        let map = value.as_object()?;
+        map.get(find_key(map, key)?)
+    })
+}
+
+/// Entry at the path, missing fields are created and non-objects on the way are replaced.
+fn path_entry<'a>(value: &'a mut Value, path: &[String]) -> &'a mut Value {
+    path.iter().fold(value, |value, key| {
+        if !value.is_object() {
+            *value = Value::Object(Map::new());
+        }
+        let map = value.as_object_mut().unwrap();
+        let key = find_key(map, key)
+            .cloned()
+            .unwrap_or_else(|| key.to_lowercase());
+        map.entry(key).or_insert(Value::Null)
+    })
+}
+
+fn path_remove(value: &mut Value, path: &[String]) {
+    let Some((last, parents)) = path.split_last() else {
+        return;
+    };
+    let Some(Value::Object(map)) = path_get_mut(value, parents) else {
+        return;
+    };
+    if let Some(key) = find_key(map, last).cloned() {
+        map.remove(&key);
+    }
+}
+
+fn path_get_mut<'a>(value: &'a mut Value, path: &[String]) -> Option<&'a mut Value> {
+    path.iter().try_fold(value, |value, key| {
+        let map = value.as_object_mut()?;
+        let key = find_key(map, key)?.clone();
+        map.get_mut(&key)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use super::*;
+
+    #[derive(Resource, Clone, Default, PartialEq, Serialize, Deserialize, Debug)]
+    struct TestSettings {
+        name: String,
+        volume: f32,
+        nested: NestedSettings,
+    }
+
+    #[derive(Clone, Default, PartialEq, Serialize, Deserialize, Debug)]
+    struct NestedSettings {
+        enabled: bool,
+        size: u32,
+    }
+
+    impl ConfigSection for TestSettings {
+        const NAME: &'static str = "test";
+    }
+
+    /// Config of a settings file with given content, or without the file.
+    fn config(name: &str, content: Option<&str>) -> (Config, PathBuf) {
+        let directory = std::env::temp_dir().join("andromeda-tests");
+        fs::create_dir_all(&directory).unwrap();
+        let file = directory.join(name);
+        match content {
+            Some(content) => fs::write(&file, content).unwrap(),
+            None => {
+                let _ = fs::remove_file(&file);
+            }
+        }
+        (Config::new(&file), file)
+    }
+
+    fn variables<'a>(
+        variables: impl IntoIterator<Item = (&'a str, &'a str)>,
+    ) -> Vec<(OsString, OsString)> {
+        variables
+            .into_iter()
+            .map(|(name, value)| (name.into(), value.into()))
+            .collect()
+    }
+
+    #[test]
+    fn applies_layers_in_order() {
+        let (mut config, _) = config(
+            "layers.json",
+            Some(r#"{"test": {"name": "file", "volume": 0.5, "nested": {"size": 1}}}"#),
+        );
+        config.read_file().unwrap();
+        config.read_variables(
+            "GAME_",
+            variables([
+                ("GAME_TEST__NAME", "env"),
+                ("GAME_TEST__NESTED__SIZE", "2"),
+                ("OTHER_TEST__VOLUME", "1"),
+            ]),
+        );
+        config.set_from_str("test.nested.size=3").unwrap();
+        config.set_override(TestSettings::NAME, ["nested", "enabled"], true);
+
+        assert_eq!(
+            config.section::<TestSettings>(),
+            Ok(TestSettings {
+                name: "env".to_string(),
+                volume: 0.5,
+                nested: NestedSettings {
+                    enabled: true,
+                    size: 3,
+                },
+            })
+        );
+    }
+
+    #[test]
+    fn parses_environment_variables() {
+        let cases = [
+            // (variables, expected section)
+            (vec![("GAME_TEST__VOLUME", "0.25")], TestSettings {
+                volume: 0.25,
+                ..default()
+            }),
+            (vec![("game_test__name", "lower")], TestSettings::default()),
+            (vec![("GAME_Test__Name", "Low")], TestSettings {
+                name: "Low".to_string(),
+                ..default()
+            }),
+            (vec![("GAME___VOLUME", "1")], TestSettings::default()),
+            // nested field wins over the whole value regardless of variable order
+            (
+                vec![
+                    ("GAME_TEST__NESTED__SIZE", "5"),
+                    ("GAME_TEST__NESTED", r#"{"enabled": true, "size": 9}"#),
+                ],
+                TestSettings {
+                    nested: NestedSettings {
+                        enabled: true,
+                        size: 5,
+                    },
+                    ..default()
+                },
+            ),
+        ];
+
+        for (vars, expected) in cases {
+            let (mut config, _) = config("env.json", None);
+            config.read_variables("GAME_", variables(vars.clone()));
+
+            assert_eq!(config.section::<TestSettings>(), Ok(expected), "{:?}", vars);
+        }
+    }
+
+    #[test]
+    fn parses_set_option() {
+        let cases = [
+            // (assignment, expected section)
+            (
+                "test.volume=0.25",
+                Ok(TestSettings {
+                    volume: 0.25,
+                    ..default()
+                }),
+            ),
+            (
+                "test.name=Low",
+                Ok(TestSettings {
+                    name: "Low".to_string(),
+                    ..default()
+                }),
+            ),
+            (
+                r#"test.name="1""#,
+                Ok(TestSettings {
+                    name: "1".to_string(),
+                    ..default()
+                }),
+            ),
+            (
+                " TEST.Volume = 2 ",
+                Ok(TestSettings {
+                    volume: 2.0,
+                    ..default()
+                }),
+            ),
+            ("other.volume=1", Ok(TestSettings::default())),
+            (
+                "test.volume",
+                Err("Expected 'section.field=value', got 'test.volume'"),
+            ),
+            ("=1", Err("Missing section in '=1'")),
+            (".volume=1", Err("Missing section in '.volume=1'")),
+            ("test.name=1", Err("Invalid 'test' settings section")),
+        ];
+
+        for (assignment, expected) in cases {
+            let (mut config, _) = config("set.json", None);
+            let section = config
+                .set_from_str(assignment)
+                .and_then(|_| config.section::<TestSettings>());
+
+            match (section, expected) {
+                (Ok(section), Ok(expected)) => assert_eq!(section, expected, "{}", assignment),
+                (Err(error), Err(expected)) => {
+                    assert!(error.starts_with(expected), "{}: {}", assignment, error)
+                }
+                (section, _) => panic!("{}: unexpected {:?}", assignment, section),
+            }
+        }
+    }
+
+    #[test]
+    fn saves_only_changed_overrides() {
+        let (mut config, file) = config("store.json", Some(r#"{"test": {"name": "file"}}"#));
+        config.read_file().unwrap();
+        config.set_from_str("test.name=cli").unwrap();
+        config.set_from_str("test.volume=0.5").unwrap();
+
+        let mut section = config.section::<TestSettings>().unwrap();
+        section.volume = 0.75;
+        config.store(&section).unwrap();
+        config.save().unwrap();
+
+        let saved: Value = serde_json::from_str(&fs::read_to_string(&file).unwrap()).unwrap();
+        assert_eq!(saved["test"]["name"], "file");
+        assert_eq!(saved["test"]["volume"], 0.75);
+
+        section.name = "menu".to_string();
+        config.store(&section).unwrap();
+        assert_eq!(config.stored["test"]["name"], "menu");
+    }
+
+    #[test]
+    fn keeps_unreadable_file() {
+        let content = r#"{"test": {"name": "#;
+        let (mut config, file) = config("unreadable.json", Some(content));
+
+        assert!(config.read_file().is_err());
+        assert_eq!(
+            config.section::<TestSettings>(),
+            Ok(TestSettings::default())
+        );
+
+        config.store(&TestSettings::default()).unwrap();
+        let error = config.save().unwrap_err();
+        assert!(error.starts_with("Settings are not saved"), "{}", error);
+        assert_eq!(fs::read_to_string(&file).unwrap(), content);
+    }
+
+    #[test]
+    fn creates_missing_file() {
+        let (mut config, file) = config("missing.json", None);
+
+        assert_eq!(config.read_file(), Ok(()));
+        config.store(&TestSettings::default()).unwrap();
+        config.save().unwrap();
+        assert!(file.exists());
+    }
+}
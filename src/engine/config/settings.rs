@@ -0,0 +1,238 @@
+use bevy::audio::Volume;
+use bevy::pbr::DirectionalLightShadowMap;
+use bevy::prelude::*;
+use bevy::window::{MonitorSelection, PresentMode, PrimaryWindow, WindowMode};
+use serde::{Deserialize, Serialize};
+use smart_default::SmartDefault;
+
+use super::{ConfigAppExt, ConfigSection};
+
+/// Applies window, graphics and audio settings when they change, debug settings
+/// are read only at startup by [`create_app`](crate::engine::create_app).
+pub struct SettingsPlugin;
+
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<DebugSettings>();
+        app.register_type::<WindowSettings>();
+        app.register_type::<GraphicsSettings>();
+        app.register_type::<AudioSettings>();
+        app.register_type::<AudioChannel>();
+        app.add_config_section::<DebugSettings>();
+        app.add_config_section::<WindowSettings>();
+        app.add_config_section::<GraphicsSettings>();
+        app.add_config_section::<AudioSettings>();
+        app.add_systems(
+            Update,
+            (
+                apply_window_settings.run_if(
+                    resource_changed::<WindowSettings>.and(not(resource_added::<WindowSettings>)),
+                ),
+                apply_graphics_settings,
+                apply_audio_settings,
+            ),
+        );
+    }
+}
+
+#[derive(Resource, Clone, SmartDefault, Serialize, Deserialize, Reflect, Debug)]
+#[reflect(Resource)]
+pub struct DebugSettings {
+    #[default = true]
+    pub version_overlay: bool,
+    pub inspector: bool,
+    pub diagnostics: bool,
+    pub profiler: bool,
+    pub console: bool,
+    /// Remote debug protocol port, disabled if not set.
+    pub remote_port: Option<u16>,
+    pub navigation_gizmos: bool,
+    pub pickup_gizmos: bool,
+    pub npc_gizmos: bool,
+    pub camera_gizmos: bool,
+    pub storage_gizmos: bool,
+    pub health_gizmos: bool,
+}
+
+impl ConfigSection for DebugSettings {
+    const NAME: &'static str = "debug";
+}
+
+#[derive(Resource, Clone, SmartDefault, Serialize, Deserialize, Reflect, Debug)]
+#[reflect(Resource)]
+pub struct WindowSettings {
+    #[default = 1280.0]
+    pub width: f32,
+    #[default = 720.0]
+    pub height: f32,
+    pub mode: WindowModeSetting,
+    #[default = true]
+    pub vsync: bool,
+}
+
+impl ConfigSection for WindowSettings {
+    const NAME: &'static str = "window";
+}
+
+impl WindowSettings {
+    pub fn apply(&self, window: &mut Window) {
+        window.resolution.set(self.width, self.height);
+        window.mode = self.mode.into();
+        window.present_mode = if self.vsync {
+            PresentMode::AutoVsync
+        } else {
+            PresentMode::AutoNoVsync
+        };
+    }
+
+    pub fn window(&self, title: String) -> Window {
+        let mut window = Window { title, ..default() };
+        self.apply(&mut window);
+        window
+    }
+}
+
+#[derive(Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, Reflect, Debug)]
+pub enum WindowModeSetting {
+    #[default]
+    Windowed,
+    BorderlessFullscreen,
+    Fullscreen,
+}
+
+impl From<WindowModeSetting> for WindowMode {
+    fn from(mode: WindowModeSetting) -> Self {
+        match mode {
+            WindowModeSetting::Windowed => WindowMode::Windowed,
+            WindowModeSetting::BorderlessFullscreen => {
+                WindowMode::BorderlessFullscreen(MonitorSelection::Current)
+            }
+            WindowModeSetting::Fullscreen => WindowMode::Fullscreen(MonitorSelection::Current),
+        }
+    }
+}
+
+#[derive(Resource, Clone, Default, Serialize, Deserialize, Reflect, Debug)]
+#[reflect(Resource)]
+pub struct GraphicsSettings {
+    pub quality: GraphicsQuality,
+}
+
+impl ConfigSection for GraphicsSettings {
+    const NAME: &'static str = "graphics";
+}
+
+#[derive(Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, Reflect, Debug)]
+pub enum GraphicsQuality {
+    Low,
+    Medium,
+    /// Same as Bevy defaults.
+    #[default]
+    High,
+}
+
+impl GraphicsQuality {
+    pub fn msaa(&self) -> Msaa {
+        match self {
+            Self::Low => Msaa::Off,
+            Self::Medium | Self::High => Msaa::Sample4,
+        }
+    }
+
+    pub fn shadow_map_size(&self) -> usize {
+        match self {
+            Self::Low => 512,
+            Self::Medium => 1024,
+            Self::High => 2048,
+        }
+    }
+}
+
+/// Volumes from 0 to 1, master is applied to all sounds, other ones only to sounds
+/// with matching [`AudioChannel`].
+#[derive(Resource, Clone, SmartDefault, Serialize, Deserialize, Reflect, Debug)]
+#[reflect(Resource)]
+pub struct AudioSettings {
+    #[default = 1.0]
+    pub master: f32,
+    #[default = 1.0]
+    pub music: f32,
+    #[default = 1.0]
+    pub sfx: f32,
+    #[default = 1.0]
+    pub ui: f32,
+}
+
+impl ConfigSection for AudioSettings {
+    const NAME: &'static str = "audio";
+}
+
+impl AudioSettings {
+    pub fn volume(&self, channel: AudioChannel) -> f32 {
+        let volume = match channel {
+            AudioChannel::Music => self.music,
+            AudioChannel::Sfx => self.sfx,
+            AudioChannel::Ui => self.ui,
+        };
+        self.master * volume
+    }
+}
+
+/// Volume group of audio player entity.
+#[derive(Component, Clone, Copy, PartialEq, Eq, Reflect, Debug)]
+#[reflect(Component)]
+// nothing plays sounds yet
+#[allow(dead_code)]
+pub enum AudioChannel {
+    Music,
+    Sfx,
+    Ui,
+}
+
+fn apply_window_settings(
+    settings: Res<WindowSettings>,
+    window: Option<Single<&mut Window, With<PrimaryWindow>>>,
+) {
+    if let Some(mut window) = window {
+        settings.apply(&mut window);
+    }
+}
+
+fn apply_graphics_settings(
+    mut commands: Commands,
+    settings: Res<GraphicsSettings>,
+    mut shadow_map: ResMut<DirectionalLightShadowMap>,
+    cameras: Query<Entity, With<Camera3d>>,
+    added_cameras: Query<Entity, Added<Camera3d>>,
+) {
+    let msaa = settings.quality.msaa();
+    if settings.is_changed() {
+        shadow_map.size = settings.quality.shadow_map_size();
+        for camera in cameras.iter() {
+            commands.entity(camera).insert(msaa);
+        }
+    } else {
+        for camera in added_cameras.iter() {
+            commands.entity(camera).insert(msaa);
+        }
+    }
+}
+
+fn apply_audio_settings(
+    mut global_volume: ResMut<GlobalVolume>,
+    settings: Res<AudioSettings>,
+    sinks: Query<(&AudioSink, &AudioChannel)>,
+    added_sinks: Query<(&AudioSink, &AudioChannel), Added<AudioSink>>,
+) {
+    if settings.is_changed() {
+        // sounds are scaled by global volume only when they start
+        global_volume.volume = Volume::new(settings.master);
+        for (sink, channel) in sinks.iter() {
+            sink.set_volume(settings.volume(*channel));
+        }
+    } else {
+        for (sink, channel) in added_sinks.iter() {
+            sink.set_volume(settings.volume(*channel));
+        }
+    }
+}
@@ -0,0 +1,419 @@
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use bevy_console::ConsoleConfiguration;
+
+use crate::engine::prototype::{PrototypeId, PrototypeRegistry};
+
+const SELECTORS: [&str; 4] = ["@p", "@s", "@e", "@e["];
+const SELECTOR_ARGUMENTS: [&str; 5] = ["type=", "name=", "distance=", "limit=", "sort="];
+const SELECTOR_SORTS: [&str; 4] = ["nearest", "furthest", "random", "arbitrary"];
+
+/// Values offered by tab completion, besides command names and values known to clap.
+#[derive(Resource, Default)]
+pub struct Completions {
+    /// Values of positional arguments, by command name and argument index.
+    arguments: HashMap<(&'static str, usize), Vec<String>>,
+    /// Prototype ids of all registries, used by `type=` of entity selectors.
+    prototypes: Vec<String>,
+}
+
+impl Completions {
+    pub fn add_prototype_ids<T: PrototypeId>(
+        &mut self,
+        registry: &PrototypeRegistry<T>,
+        arguments: &[(&'static str, usize)],
+    ) {
+        let ids = self.add_ids(registry.ids(), arguments);
+        self.prototypes.extend(ids);
+    }
+
+    /// Offers ids as values of the arguments and returns them sorted.
+    pub fn add_ids<T: PrototypeId>(
+        &mut self,
+        ids: impl IntoIterator<Item = T>,
+        arguments: &[(&'static str, usize)],
+    ) -> Vec<String> {
+        let mut ids = ids.into_iter().map(|id| id.to_string()).collect::<Vec<_>>();
+        ids.sort();
+
+        for argument in arguments {
+            self.arguments.insert(*argument, ids.clone());
+        }
+        ids
+    }
+}
+
+#[derive(Default, Debug)]
+pub struct Completion {
+    /// Byte offset of the completed word in the line.
+    pub start: usize,
+    pub candidates: Vec<Candidate>,
+}
+
+#[derive(Debug)]
+pub struct Candidate {
+    pub value: String,
+    /// Shown next to the value, like prototype of an entity.
+    pub hint: Option<String>,
+}
+
+impl Completion {
+    /// Line with the word replaced by the only candidate, or by the prefix shared by all of them.
+    pub fn apply(&self, line: &str) -> Option<String> {
+        let value = match self.candidates.as_slice() {
+            [] => return None,
+            [candidate] => {
+                let value = &candidate.value;
+                // selector arguments continue right after the completed part,
+                // until the selector is closed
+                let open_selector = value.starts_with("@e[") && !value.ends_with(']');
+                match value.ends_with(['[', '=']) || open_selector {
+                    true => value.clone(),
+                    false => format!("{} ", value),
+                }
+            }
+            [first, rest @ ..] => rest.iter().fold(first.value.clone(), |prefix, candidate| {
+                common_prefix(&prefix, &candidate.value).to_string()
+            }),
+        };
+
+        let word = &line[self.start..];
+        (value.len() >= word.len() && value != word)
+            .then(|| format!("{}{}", &line[..self.start], value))
+    }
+}
+
+/// Candidates for the last word of the line.
+pub fn complete(
+    line: &str,
+    config: &ConsoleConfiguration,
+    completions: &Completions,
+    entities: &[(Entity, String)],
+) -> Completion {
+    let start = line.rfind(char::is_whitespace).map_or(0, |index| index + 1);
+    let word = &line[start..];
+    let mut words = line[..start].split_whitespace();
+
+    let Some(command) = words.next() else {
+        return Completion {
+            start,
+            candidates: candidates(config.commands.keys().copied(), word),
+        };
+    };
+
+    if let Some(selector) = word.strip_prefix('@') {
+        return complete_selector(start, selector, completions);
+    }
+
+    if word.starts_with(|c: char| c.is_ascii_digit()) {
+        let candidates = entities
+            .iter()
+            .map(|(entity, prototype)| (entity.to_string(), prototype))
+            .filter(|(entity, _)| entity.starts_with(word))
+            .map(|(value, prototype)| Candidate {
+                value,
+                hint: Some(prototype.clone()),
+            })
+            .collect();
+        return Completion { start, candidates };
+    }
+
+    // flags are not positional arguments
+    let index = words.filter(|word| !word.starts_with("--")).count();
+    let mut values = completions
+        .arguments
+        .get(&(command, index))
+        .cloned()
+        .unwrap_or_default();
+
+    if let Some(argument) = config
+        .commands
+        .get(command)
+        .and_then(|command| command.get_positionals().nth(index))
+    {
+        let possible = argument.get_possible_values();
+        values.extend(possible.iter().map(|value| value.get_name().to_string()));
+    }
+
+    Completion {
+        start,
+        candidates: candidates(values.iter().map(String::as_str), word),
+    }
+}
+
+fn complete_selector(start: usize, selector: &str, completions: &Completions) -> Completion {
+    let Some(arguments) = selector.strip_prefix("e[") else {
+        return Completion {
+            start,
+            candidates: candidates(SELECTORS, &format!("@{}", selector)),
+        };
+    };
+
+    // only the argument after the last separator is completed
+    let part = arguments.rfind(',').map_or(0, |index| index + 1);
+    let (done, argument) = arguments.split_at(part);
+    let prefix = format!("@e[{}", done);
+
+    let candidates = match argument.split_once('=') {
+        None => candidates(SELECTOR_ARGUMENTS, argument),
+        Some(("type", value)) => {
+            candidates(completions.prototypes.iter().map(String::as_str), value)
+                .into_iter()
+                .map(|candidate| Candidate {
+                    value: format!("type={}", candidate.value),
+                    ..candidate
+                })
+                .collect()
+        }
+        Some(("sort", value)) => candidates(SELECTOR_SORTS, value)
+            .into_iter()
+            .map(|candidate| Candidate {
+                value: format!("sort={}", candidate.value),
+                ..candidate
+            })
+            .collect(),
+        Some(_) => Vec::new(),
+    };
+
+    Completion {
+        start,
+        candidates: candidates
+            .into_iter()
+            .map(|candidate| Candidate {
+                value: format!("{}{}", prefix, candidate.value),
+                ..candidate
+            })
+            .collect(),
+    }
+}
+
+fn candidates<'a>(values: impl IntoIterator<Item = &'a str>, word: &str) -> Vec<Candidate> {
+    let word = word.to_lowercase();
+    values
+        .into_iter()
+        .filter(|value| value.to_lowercase().starts_with(&word))
+        .map(|value| Candidate {
+            value: value.to_string(),
+            hint: None,
+        })
+        .collect()
+}
+
+fn common_prefix<'a>(a: &'a str, b: &str) -> &'a str {
+    let length = a
+        .char_indices()
+        .zip(b.chars())
+        .find(|((_, a), b)| !a.eq_ignore_ascii_case(b))
+        .map_or(a.len().min(b.len()), |((index, _), _)| index);
+    &a[..length]
+}
+
+/// The most similar value, if it is close enough to be a typo.
+pub fn did_you_mean<'a>(input: &str, values: impl IntoIterator<Item = &'a str>) -> Option<&'a str> {
+    if input.is_empty() {
+        return None;
+    }
+
+    let input = input.to_lowercase();
+    let max_distance = (input.chars().count() / 3).max(2);
+    values
+        .into_iter()
+        .map(|value| (value, edit_distance(&input, &value.to_lowercase())))
+        .filter(|(_, distance)| *distance <= max_distance)
+        .min_by_key(|(_, distance)| *distance)
+        .map(|(value, _)| value)
+}
+
+/// Levenshtein distance, number of inserted, removed or replaced characters.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<_>>();
+    let mut previous = (0..=b.len()).collect::<Vec<_>>();
+    for (i, a) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, b) in b.iter().enumerate() {
+            let replace = previous[j] + usize::from(a != *b);
+            current.push(replace.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::{Arg, Command};
+
+    use super::*;
+
+    fn config() -> ConsoleConfiguration {
+        let mut config = ConsoleConfiguration::default();
+        config.commands.insert(
+            "spawn-character",
+            Command::new("spawn-character")
+                .arg(Arg::new("id"))
+                .arg(Arg::new("position")),
+        );
+        config
+            .commands
+            .insert("spawn-item", Command::new("spawn-item").arg(Arg::new("id")));
+        config.commands.insert(
+            "gizmos",
+            Command::new("gizmos").arg(Arg::new("overlay").value_parser(["navigation", "npc"])),
+        );
+        config
+    }
+
+    fn completions() -> Completions {
+        let mut completions = Completions::default();
+        completions.arguments.insert(("spawn-character", 0), vec![
+            "Enemy".to_string(),
+            "Player".to_string(),
+        ]);
+        completions.prototypes = vec!["Enemy".to_string(), "Player".to_string()];
+        completions
+    }
+
+    #[test]
+    fn completes_last_word() {
+        let (config, completions) = (config(), completions());
+        let entities = [
+            (Entity::from_raw(12), "Enemy".to_string()),
+            (Entity::from_raw(13), "Player".to_string()),
+        ];
+
+        let cases: [(&str, &[&str], Option<&str>); 19] = [
+            // (line, candidates, line after applying the completion)
+            ("", &["gizmos", "spawn-character", "spawn-item"], None),
+            ("sp", &["spawn-character", "spawn-item"], Some("spawn-")),
+            ("spawn-c", &["spawn-character"], Some("spawn-character ")),
+            ("SPAWN-I", &["spawn-item"], Some("spawn-item ")),
+            ("spawn-character ", &["Enemy", "Player"], None),
+            (
+                "spawn-character en",
+                &["Enemy"],
+                Some("spawn-character Enemy "),
+            ),
+            (
+                "spawn-character --at en",
+                &["Enemy"],
+                Some("spawn-character --at Enemy "),
+            ),
+            ("spawn-character Enemy ", &[], None),
+            (
+                "spawn-character Enemy",
+                &["Enemy"],
+                Some("spawn-character Enemy "),
+            ),
+            ("gizmos n", &["navigation", "npc"], None),
+            ("gizmos na", &["navigation"], Some("gizmos navigation ")),
+            ("tp @e", &["@e", "@e["], None),
+            ("tp @e[ty", &["@e[type="], Some("tp @e[type=")),
+            (
+                "tp @e[type=en",
+                &["@e[type=Enemy"],
+                Some("tp @e[type=Enemy"),
+            ),
+            (
+                "tp @e[type=Enemy,so",
+                &["@e[type=Enemy,sort="],
+                Some("tp @e[type=Enemy,sort="),
+            ),
+            (
+                "tp @e[sort=f",
+                &["@e[sort=furthest"],
+                Some("tp @e[sort=furthest"),
+            ),
+            ("tp @e[limit=", &[], None),
+            ("kill 1", &["12v1", "13v1"], None),
+            ("kill 12", &["12v1"], Some("kill 12v1 ")),
+        ];
+
+        for (line, candidates, applied) in cases {
+            let completion = complete(line, &config, &completions, &entities);
+            let values = completion
+                .candidates
+                .iter()
+                .map(|c| c.value.as_str())
+                .collect::<Vec<_>>();
+
+            assert_eq!(values, candidates, "candidates of '{}'", line);
+            assert_eq!(
+                completion.apply(line).as_deref(),
+                applied,
+                "completion of '{}'",
+                line
+            );
+        }
+    }
+
+    #[test]
+    fn completes_entity_with_prototype_hint() {
+        let entities = [(Entity::from_raw(12), "Enemy".to_string())];
+        let completion = complete("kill 1", &config(), &completions(), &entities);
+
+        assert_eq!(completion.candidates[0].hint.as_deref(), Some("Enemy"));
+    }
+
+    #[test]
+    fn sorts_added_ids() {
+        let mut completions = Completions::default();
+        let ids = completions.add_ids([10_u32, 2, 1], &[("give", 0)]);
+
+        assert_eq!(ids, ["1", "10", "2"]);
+        assert_eq!(completions.arguments[&("give", 0)], ids);
+    }
+
+    #[test]
+    fn finds_common_prefix() {
+        let cases = [
+            ("abc", "abd", "ab"),
+            ("abc", "ab", "ab"),
+            ("ab", "abc", "ab"),
+            ("Enemy", "enemies", "Enem"),
+            ("abc", "xyz", ""),
+            ("", "abc", ""),
+            ("żółw", "żółty", "żół"),
+        ];
+
+        for (a, b, expected) in cases {
+            assert_eq!(common_prefix(a, b), expected, "'{}' and '{}'", a, b);
+        }
+    }
+
+    #[test]
+    fn suggests_similar_value() {
+        let values = ["Player", "Bandits", "Merchants"];
+        let cases = [
+            ("Bandit", Some("Bandits")),
+            ("bandits", Some("Bandits")),
+            ("Palyer", Some("Player")),
+            ("Merchant", Some("Merchants")),
+            ("Wolves", None),
+            ("", None),
+        ];
+
+        for (input, expected) in cases {
+            assert_eq!(did_you_mean(input, values), expected, "{}", input);
+        }
+
+        // ties go to the first value
+        assert_eq!(did_you_mean("ab", ["ac", "ad"]), Some("ac"));
+    }
+
+    #[test]
+    fn measures_edit_distance() {
+        let cases = [
+            ("", "", 0),
+            ("abc", "", 3),
+            ("", "abc", 3),
+            ("abc", "abc", 0),
+            ("kitten", "sitting", 3),
+            ("flaw", "lawn", 2),
+            ("żółw", "żółty", 2),
+        ];
+
+        for (a, b, expected) in cases {
+            assert_eq!(edit_distance(a, b), expected, "'{}' and '{}'", a, b);
+        }
+    }
+}
@@ -0,0 +1,1525 @@
+mod completion;
+mod script;
+mod selector;
+mod ui;
+
+use std::marker::PhantomData;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use bevy::prelude::*;
+use bevy::reflect::serde::{TypedReflectDeserializer, TypedReflectSerializer};
+use bevy::reflect::{GetPath, TypeRegistry};
+use bevy_console::{
+    AddConsoleCommand, ConsoleCommand, ConsoleCommandEntered, ConsoleConfiguration, ConsoleOpen,
+    ConsoleSet, NamedCommand, PrintConsoleLine,
+};
+use bevy_inspector_egui::bevy_egui::EguiPlugin;
+use clap::{ArgAction, Parser, ValueEnum};
+use completion::{Completions, did_you_mean};
+use script::{ScriptRunner, run_scripts};
+use selector::{EntitySelector, Selectors};
+use serde::de::DeserializeSeed;
+use ui::{
+    ClearCommand, ConsoleState, ExitCommand, HelpCommand, console_ui, log_console_lines,
+    receive_console_lines,
+};
+
+use super::camera::track::{CameraKeyframe, CameraTrack};
+use super::camera::{CameraMode, GameCamera};
+use super::character::controller::CharacterController;
+use super::character::faction::FactionRelations;
+use super::character::player::PlayerOrder;
+use super::character::{Damage, Health, Invulnerable};
+use super::debug_gizmos::DebugGizmos;
+use super::input::GameplayInputSet;
+use super::input::bindings::{InputAction, InputBinding, InputMap, PendingRebind};
+use super::input::context::{InputContext, InputContexts};
+use super::item::storage::{InsertItemCommand, ItemStorage};
+use super::navigation::{NavPath, NavigationDebug};
+use super::profiling::ProfilerHud;
+use super::prototype::{PrototypeId, PrototypeInstance, PrototypeRegistry};
+use super::time_control::TimeControl;
+
+pub struct DebugConsolePlugin<CharacterId: PrototypeId, ItemId: PrototypeId, FactionId: PrototypeId>
+{
+    /// Scripts run at startup, in order.
+    exec_files: Vec<PathBuf>,
+    history_file: PathBuf,
+    /// Runs commands and scripts without the window, printed lines go to the log.
+    headless: bool,
+    _character_id: PhantomData<CharacterId>,
+    _item_id: PhantomData<ItemId>,
+    _faction_id: PhantomData<FactionId>,
+}
+
+impl<CharacterId: PrototypeId, ItemId: PrototypeId, FactionId: PrototypeId>
+    DebugConsolePlugin<CharacterId, ItemId, FactionId>
+{
+    pub fn new(exec_files: Vec<PathBuf>, history_file: PathBuf, headless: bool) -> Self {
+        Self {
+            exec_files,
+            history_file,
+            headless,
+            _character_id: default(),
+            _item_id: default(),
+            _faction_id: default(),
+        }
+    }
+}
+
+impl<CharacterId: PrototypeId, ItemId: PrototypeId, FactionId: PrototypeId> Plugin
+    for DebugConsolePlugin<CharacterId, ItemId, FactionId>
+{
+    fn build(&self, app: &mut bevy::prelude::App) {
+        // own window replaces the one of ConsolePlugin, so only its commands and events are used
+        if !self.headless && !app.is_plugin_added::<EguiPlugin>() {
+            app.add_plugins(EguiPlugin);
+        }
+        app.init_resource::<ConsoleConfiguration>();
+        app.init_resource::<ConsoleOpen>();
+        app.insert_resource(ConsoleState::new(self.history_file.clone()));
+        app.add_event::<ConsoleCommandEntered>();
+        app.add_event::<PrintConsoleLine>();
+        app.configure_sets(
+            Update,
+            (
+                ConsoleSet::Commands
+                    .after(ConsoleSet::ConsoleUI)
+                    .run_if(on_event::<ConsoleCommandEntered>),
+                ConsoleSet::PostCommands.after(ConsoleSet::Commands),
+            ),
+        );
+        if self.headless {
+            app.add_systems(Update, log_console_lines.in_set(ConsoleSet::PostCommands));
+        } else {
+            app.add_systems(
+                Update,
+                (
+                    console_ui.in_set(ConsoleSet::ConsoleUI),
+                    receive_console_lines.in_set(ConsoleSet::PostCommands),
+                ),
+            );
+        }
+        app.add_console_command::<HelpCommand, _>(ui::help);
+        app.add_console_command::<ClearCommand, _>(ui::clear);
+        app.add_console_command::<ExitCommand, _>(ui::exit);
+
+        app.add_console_command::<ListCharactersCommand, _>(list_characters::<CharacterId>);
+        app.add_console_command::<SpawnCharacterCommand, _>(spawn_character::<CharacterId>);
+        app.add_console_command::<DespawnCharactersCommand, _>(despawn_characters::<CharacterId>);
+        app.add_console_command::<ListItemsCommand, _>(list_items::<ItemId>);
+        app.add_console_command::<SpawnItemCommand, _>(spawn_item::<ItemId>);
+        app.add_console_command::<DespawnItemsCommand, _>(despawn_items::<ItemId>);
+        app.add_console_command::<FactionRelationCommand, _>(faction_relation::<FactionId>);
+        app.add_console_command::<SetReputationCommand, _>(set_reputation::<FactionId>);
+        app.add_console_command::<ListBindingsCommand, _>(list_bindings);
+        app.add_console_command::<BindCommand, _>(bind);
+        app.add_console_command::<UnbindCommand, _>(unbind);
+        app.add_console_command::<RebindCommand, _>(rebind);
+        app.add_console_command::<InputContextCommand, _>(input_context);
+        app.init_resource::<CameraTrackDraft>();
+        app.add_console_command::<CameraModeCommand, _>(camera_mode);
+        app.add_console_command::<CameraTargetCommand, _>(camera_target);
+        app.add_console_command::<CameraKeyCommand, _>(camera_key);
+        app.add_console_command::<GetCommand, _>(get_component);
+        app.add_console_command::<SetCommand, _>(set_component);
+        app.add_console_command::<GiveCommand, _>(give::<ItemId>);
+        app.add_console_command::<TeleportCommand, _>(teleport);
+        app.add_console_command::<HealCommand, _>(heal);
+        app.add_console_command::<DamageCommand, _>(damage);
+        app.add_console_command::<KillCommand, _>(kill);
+        app.add_console_command::<GodCommand, _>(god);
+        app.add_console_command::<NoclipCommand, _>(noclip);
+        app.add_console_command::<ExecCommand, _>(exec);
+        app.add_console_command::<PauseCommand, _>(pause);
+        app.add_console_command::<StepCommand, _>(step);
+        app.add_console_command::<TimeScaleCommand, _>(time_scale);
+        app.add_console_command::<GizmosCommand, _>(gizmos);
+        app.add_console_command::<ProfilerCommand, _>(profiler);
+
+        // scripts are a stack, so the first file has to be pushed last
+        let mut runner = ScriptRunner::default();
+        for file in self.exec_files.iter().rev() {
+            if let Err(error) = runner.exec(file) {
+                error!("{}", error);
+            }
+        }
+        app.insert_resource(runner);
+        app.add_systems(Update, run_scripts.before(ConsoleSet::Commands));
+
+        // registries and relations are created before plugins, so their ids are already known
+        let mut completions = Completions::default();
+        if let Some(registry) = app.world().get_resource::<PrototypeRegistry<CharacterId>>() {
+            completions.add_prototype_ids(registry, &[
+                (ListCharactersCommand::name(), 0),
+                (SpawnCharacterCommand::name(), 0),
+                (DespawnCharactersCommand::name(), 0),
+            ]);
+        }
+        if let Some(registry) = app.world().get_resource::<PrototypeRegistry<ItemId>>() {
+            completions.add_prototype_ids(registry, &[
+                (ListItemsCommand::name(), 0),
+                (SpawnItemCommand::name(), 0),
+                (DespawnItemsCommand::name(), 0),
+                (GiveCommand::name(), 0),
+            ]);
+        }
+        if let Some(relations) = app.world().get_resource::<FactionRelations<FactionId>>() {
+            completions.add_ids(relations.factions(), &[
+                (FactionRelationCommand::name(), 0),
+                (FactionRelationCommand::name(), 1),
+                (SetReputationCommand::name(), 0),
+                (SetReputationCommand::name(), 1),
+            ]);
+        }
+        app.insert_resource(completions);
+
+        app.add_systems(
+            PreUpdate,
+            sync_console_input_context
+                .run_if(resource_changed::<ConsoleOpen>)
+                .before(GameplayInputSet),
+        );
+    }
+}
+
+#[derive(Clone)]
+enum Position {
+    Custom(Vec3),
+    Entity(EntitySelector),
+}
+
+impl FromStr for Position {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        const ERROR_SUFFIX: &str =
+            "Supported values are entity selectors (like @p), (x,z), (x,y,z).";
+
+        if let Ok(selector) = EntitySelector::from_str(input) {
+            return Ok(Self::Entity(selector));
+        }
+
+        let input = input.trim().trim_matches(['(', ')']).to_lowercase();
+
+        let mut split = input.split_terminator(',');
+        let Some(x) = split.next().and_then(|x| f32::from_str(x).ok()) else {
+            return Err(format!("Cannot parse X coordinate. {}", ERROR_SUFFIX));
+        };
+
+        let Some(y) = split.next().and_then(|x| f32::from_str(x).ok()) else {
+            return Err(format!("Cannot parse Y/Z coordinate. {}", ERROR_SUFFIX));
+        };
+
+        match split.next() {
+            Some(z) => match f32::from_str(z) {
+                Ok(z) => Ok(Self::Custom(Vec3::new(x, y, z))),
+                Err(_) => Err(format!("Cannot parse Z coordinate. {}", ERROR_SUFFIX)),
+            },
+            None => Ok(Self::Custom(Vec3::new(x, 0.0, y))),
+        }
+    }
+}
+
+impl Position {
+    fn resolve(
+        &self,
+        selectors: &mut Selectors,
+        transforms: &Query<&GlobalTransform>,
+    ) -> Result<Vec3, String> {
+        match self {
+            Self::Custom(translation) => Ok(*translation),
+            Self::Entity(selector) => selectors
+                .select_one(selector)
+                .and_then(|entity| transforms.get(entity).map_err(|e| e.to_string()))
+                .map(|transform| transform.translation()),
+        }
+    }
+}
+
+/// Parses prototype or faction id, mistyped ones get the closest of `ids` suggested.
+fn parse_id<T: PrototypeId>(input: &str, ids: impl IntoIterator<Item = T>) -> Result<T, String> {
+    T::from_str(input).map_err(|_| {
+        let mut ids = ids.into_iter().map(|id| id.to_string()).collect::<Vec<_>>();
+        ids.sort();
+        match did_you_mean(input, ids.iter().map(String::as_str)) {
+            Some(id) => format!("Cannot parse id '{}', did you mean '{}'?", input, id),
+            None => format!(
+                "Cannot parse id '{}'. Supported ids are {}.",
+                input,
+                ids.join(", ")
+            ),
+        }
+    })
+}
+
+macro_rules! generate_registry_commands {
+    (
+        $spawn_name:literal, $spawn_about:literal, $spawn_command:ident, $spawn_system:ident,
+        $despawn_name:literal, $despawn_about:literal, $despawn_command:ident, $despawn_system:ident,
+        $list_name:literal, $list_about:literal, $list_command:ident, $list_system:ident
+    ) => {
+        #[derive(Parser, ConsoleCommand)]
+        #[command(name = $list_name, about = $list_about)]
+        struct $list_command {
+            #[arg(default_value = None)]
+            id: Option<String>,
+        }
+
+        #[derive(Parser, ConsoleCommand)]
+        #[command(name = $spawn_name, about = $spawn_about)]
+        struct $spawn_command {
+            id: String,
+            #[arg(value_parser = clap::value_parser!(Position))]
+            position: Position,
+        }
+
+        #[derive(Parser, ConsoleCommand)]
+        #[command(name = $despawn_name, about = $despawn_about)]
+        struct $despawn_command {
+            #[arg(help = "Prototype id or entity selector (like @e[type=Enemy,limit=2])")]
+            id: String,
+            #[arg(action = ArgAction::Set, default_value_t = false)]
+            all: bool,
+        }
+
+        fn $list_system<T: PrototypeId>(
+            mut command: ConsoleCommand<$list_command>,
+            registry: Res<PrototypeRegistry<T>>,
+            query: Populated<(Entity, Option<&Name>, &PrototypeInstance<T>)>,
+        ) {
+            let Some(Ok($list_command { id })) = command.take() else {
+                return;
+            };
+
+            match id {
+                None => {
+                    for (entity, name, _) in query.iter() {
+                        command.reply(format!(
+                            "{} - {}",
+                            entity,
+                            name.map(|n| n.as_str()).unwrap_or("<None>")
+                        ));
+                    }
+                }
+                Some(id) => {
+                    let id = match parse_id(&id, registry.ids()) {
+                        Ok(id) => id,
+                        Err(error) => {
+                            command.reply(error);
+                            return;
+                        }
+                    };
+
+                    for (entity, name, prototype) in query.iter() {
+                        if prototype.id() == id {
+                            command.reply(format!(
+                                "{} - {}",
+                                entity,
+                                name.map(|n| n.as_str()).unwrap_or("<None>")
+                            ));
+                        }
+                    }
+                }
+            }
+        }
+
+        fn $spawn_system<T: PrototypeId>(
+            mut command: ConsoleCommand<$spawn_command>,
+            mut commands: Commands,
+            registry: Res<PrototypeRegistry<T>>,
+            mut selectors: Selectors,
+            transforms: Query<&GlobalTransform>,
+        ) {
+            let Some(Ok($spawn_command { id, position })) = command.take() else {
+                return;
+            };
+
+            let id = match parse_id(&id, registry.ids()) {
+                Ok(id) => id,
+                Err(error) => {
+                    command.reply(error);
+                    return;
+                }
+            };
+
+            let transform = match position.resolve(&mut selectors, &transforms) {
+                Ok(translation) => Transform::from_translation(translation),
+                Err(error) => {
+                    command.reply(format!("Cannot spawn at entity position. {}", error));
+                    return;
+                }
+            };
+
+            let entity = registry.spawn_at(id, transform, &mut commands);
+            command.reply(format!(
+                "{} ({}) has been successfully spawned at {}",
+                id, entity, transform.translation
+            ));
+        }
+
+        fn $despawn_system<T: PrototypeId>(
+            mut command: ConsoleCommand<$despawn_command>,
+            mut commands: Commands,
+            registry: Res<PrototypeRegistry<T>>,
+            query: Query<(Entity, &PrototypeInstance<T>)>,
+            mut selectors: Selectors,
+        ) {
+            let Some(Ok($despawn_command { id, all })) = command.take() else {
+                return;
+            };
+
+            if let Ok(selector) = EntitySelector::from_str(&id) {
+                let entities = match selectors.select(&selector) {
+                    Ok(entities) => entities,
+                    Err(error) => {
+                        command.reply(error);
+                        return;
+                    }
+                };
+
+                for entity in entities {
+                    match query.get(entity) {
+                        Ok((entity, prototype)) => {
+                            commands.entity(entity).despawn_recursive();
+                            command.reply(format!(
+                                "{} ({}) has been successfully despawned",
+                                prototype.id(),
+                                entity
+                            ));
+                        }
+                        Err(_) => command.reply(format!(
+                            "{} was not spawned from this kind of prototype",
+                            entity
+                        )),
+                    }
+                }
+                return;
+            }
+
+            let id = match parse_id(&id, registry.ids()) {
+                Ok(id) => id,
+                Err(error) => {
+                    command.reply(error);
+                    return;
+                }
+            };
+
+            for (entity, prototype) in query.iter() {
+                if prototype.id() == id {
+                    commands.entity(entity).despawn_recursive();
+                    command.reply(format!(
+                        "{} ({}) has been successfully despawned",
+                        id, entity
+                    ));
+
+                    if !all {
+                        return;
+                    }
+                }
+            }
+        }
+    };
+}
+
+generate_registry_commands!(
+    "spawn-character",
+    "Spawns new instance of a character in world",
+    SpawnCharacterCommand,
+    spawn_character,
+    "despawn-characters",
+    "Despawns instances of a character from a world",
+    DespawnCharactersCommand,
+    despawn_characters,
+    "list-characters",
+    "Lists instances of a character in a world",
+    ListCharactersCommand,
+    list_characters
+);
+
+generate_registry_commands!(
+    "spawn-item",
+    "Spawns new instance of an item in world",
+    SpawnItemCommand,
+    spawn_item,
+    "despawn-items",
+    "Despawns instances of an item from a world",
+    DespawnItemsCommand,
+    despawn_items,
+    "list-items",
+    "Lists instances of an item in a world",
+    ListItemsCommand,
+    list_items
+);
+
+#[derive(Parser, ConsoleCommand)]
+#[command(
+    name = "faction-relation",
+    about = "Shows how one faction sees another faction"
+)]
+struct FactionRelationCommand {
+    faction: String,
+    towards: String,
+}
+
+#[derive(Parser, ConsoleCommand)]
+#[command(
+    name = "set-reputation",
+    about = "Sets reputation of one faction towards another faction"
+)]
+struct SetReputationCommand {
+    faction: String,
+    towards: String,
+    #[arg(allow_negative_numbers = true)]
+    reputation: i16,
+    #[arg(action = ArgAction::Set, default_value_t = false)]
+    mutual: bool,
+}
+
+fn faction_relation<T: PrototypeId>(
+    mut command: ConsoleCommand<FactionRelationCommand>,
+    relations: Res<FactionRelations<T>>,
+) {
+    let Some(Ok(FactionRelationCommand { faction, towards })) = command.take() else {
+        return;
+    };
+
+    let ids = relations.factions();
+    let (faction, towards) = match (
+        parse_id(&faction, ids.iter().copied()),
+        parse_id(&towards, ids.iter().copied()),
+    ) {
+        (Ok(faction), Ok(towards)) => (faction, towards),
+        (Err(error), _) | (_, Err(error)) => {
+            command.reply(error);
+            return;
+        }
+    };
+
+    command.reply(format!(
+        "{} is {} towards {} (reputation {})",
+        faction,
+        relations.relationship(faction, towards),
+        towards,
+        relations.reputation(faction, towards)
+    ));
+}
+
+fn set_reputation<T: PrototypeId>(
+    mut command: ConsoleCommand<SetReputationCommand>,
+    mut relations: ResMut<FactionRelations<T>>,
+) {
+    let Some(Ok(SetReputationCommand {
+        faction,
+        towards,
+        reputation,
+        mutual,
+    })) = command.take()
+    else {
+        return;
+    };
+
+    let ids = relations.factions();
+    let (faction, towards) = match (
+        parse_id(&faction, ids.iter().copied()),
+        parse_id(&towards, ids.iter().copied()),
+    ) {
+        (Ok(faction), Ok(towards)) => (faction, towards),
+        (Err(error), _) | (_, Err(error)) => {
+            command.reply(error);
+            return;
+        }
+    };
+
+    if mutual {
+        relations.set_mutual_reputation(faction, towards, reputation);
+    } else {
+        relations.set_reputation(faction, towards, reputation);
+    }
+
+    command.reply(format!(
+        "{} is now {} towards {} (reputation {})",
+        faction,
+        relations.relationship(faction, towards),
+        towards,
+        relations.reputation(faction, towards)
+    ));
+}
+
+#[derive(Parser, ConsoleCommand)]
+#[command(name = "list-bindings", about = "Lists input bindings of every action")]
+struct ListBindingsCommand;
+
+#[derive(Parser, ConsoleCommand)]
+#[command(
+    name = "bind",
+    about = "Adds binding, for example Key(KeyW), to an input action"
+)]
+struct BindCommand {
+    action: String,
+    binding: String,
+}
+
+#[derive(Parser, ConsoleCommand)]
+#[command(name = "unbind", about = "Removes binding from an input action")]
+struct UnbindCommand {
+    action: String,
+    binding: String,
+}
+
+#[derive(Parser, ConsoleCommand)]
+#[command(
+    name = "rebind",
+    about = "Binds next pressed key or mouse button to an input action, Escape cancels"
+)]
+struct RebindCommand {
+    action: String,
+}
+
+#[derive(Parser, ConsoleCommand)]
+#[command(
+    name = "input-context",
+    about = "Lists input context stack, top context is active"
+)]
+struct InputContextCommand {
+    #[arg(long, help = "Context to put on top of the stack")]
+    push: Option<String>,
+    #[arg(long, help = "Context to remove from the stack")]
+    remove: Option<String>,
+}
+
+#[derive(Clone, ValueEnum)]
+enum CameraModeName {
+    Follow,
+    FreeFly,
+    Fixed,
+    Track,
+}
+
+#[derive(Parser, ConsoleCommand)]
+#[command(
+    name = "camera-mode",
+    about = "Switches camera mode, free-fly and fixed start at the current camera pose"
+)]
+struct CameraModeCommand {
+    mode: CameraModeName,
+    #[arg(long, help = "Loop the track recorded with camera-key")]
+    looping: bool,
+}
+
+#[derive(Parser, ConsoleCommand)]
+#[command(
+    name = "camera-target",
+    about = "Makes the camera follow an entity (like 12v1 or @p), default target is used without it"
+)]
+struct CameraTargetCommand {
+    #[arg(value_parser = clap::value_parser!(EntitySelector))]
+    entity: Option<EntitySelector>,
+}
+
+#[derive(Parser, ConsoleCommand)]
+#[command(
+    name = "camera-key",
+    about = "Adds current camera pose as a keyframe of the track played by camera-mode track"
+)]
+struct CameraKeyCommand {
+    #[arg(help = "Seconds from the track start")]
+    time: Option<f32>,
+    #[arg(long, help = "Remove all keyframes")]
+    clear: bool,
+}
+
+#[derive(Parser, ConsoleCommand)]
+#[command(
+    name = "get",
+    about = "Prints reflected component or its field, like get @p Health.current"
+)]
+struct GetCommand {
+    #[arg(value_parser = clap::value_parser!(EntitySelector))]
+    entity: EntitySelector,
+    #[arg(help = "Component with optional field path, components are listed without it")]
+    path: Option<String>,
+}
+
+#[derive(Parser, ConsoleCommand)]
+#[command(
+    name = "set",
+    about = "Changes reflected component or its field to value in RON format, like set @p Speed.0 8.0"
+)]
+struct SetCommand {
+    #[arg(value_parser = clap::value_parser!(EntitySelector))]
+    entity: EntitySelector,
+    path: String,
+    #[arg(required = true, num_args = 1.., allow_hyphen_values = true)]
+    value: Vec<String>,
+}
+
+#[derive(Parser, ConsoleCommand)]
+#[command(
+    name = "give",
+    about = "Spawns items and puts them into storage of the target, player by default"
+)]
+struct GiveCommand {
+    id: String,
+    #[arg(default_value_t = 1)]
+    count: u16,
+    #[arg(value_parser = clap::value_parser!(EntitySelector), default_value = "@p")]
+    target: EntitySelector,
+}
+
+#[derive(Parser, ConsoleCommand)]
+#[command(
+    name = "tp",
+    about = "Teleports entities to a position or another entity"
+)]
+struct TeleportCommand {
+    #[arg(value_parser = clap::value_parser!(EntitySelector))]
+    target: EntitySelector,
+    #[arg(value_parser = clap::value_parser!(Position))]
+    position: Position,
+}
+
+#[derive(Parser, ConsoleCommand)]
+#[command(
+    name = "heal",
+    about = "Restores health, to the maximum without amount"
+)]
+struct HealCommand {
+    #[arg(value_parser = clap::value_parser!(EntitySelector), default_value = "@p")]
+    target: EntitySelector,
+    amount: Option<u16>,
+}
+
+#[derive(Parser, ConsoleCommand)]
+#[command(name = "damage", about = "Removes health, characters without any die")]
+struct DamageCommand {
+    #[arg(value_parser = clap::value_parser!(EntitySelector))]
+    target: EntitySelector,
+    amount: u16,
+}
+
+#[derive(Parser, ConsoleCommand)]
+#[command(name = "kill", about = "Kills characters, even invulnerable ones")]
+struct KillCommand {
+    #[arg(value_parser = clap::value_parser!(EntitySelector))]
+    target: EntitySelector,
+}
+
+#[derive(Parser, ConsoleCommand)]
+#[command(
+    name = "god",
+    about = "Toggles invulnerability, of the player by default"
+)]
+struct GodCommand {
+    #[arg(value_parser = clap::value_parser!(EntitySelector), default_value = "@p")]
+    target: EntitySelector,
+}
+
+#[derive(Parser, ConsoleCommand)]
+#[command(
+    name = "noclip",
+    about = "Toggles movement without gravity and collisions, of the player by default"
+)]
+struct NoclipCommand {
+    #[arg(value_parser = clap::value_parser!(EntitySelector), default_value = "@p")]
+    target: EntitySelector,
+}
+
+#[derive(Parser, ConsoleCommand)]
+#[command(
+    name = "exec",
+    about = "Runs console commands from a file, with comments (#), var, alias and wait"
+)]
+struct ExecCommand {
+    file: PathBuf,
+}
+
+#[derive(Parser, ConsoleCommand)]
+#[command(name = "pause", about = "Pauses or resumes gameplay time")]
+struct PauseCommand;
+
+#[derive(Parser, ConsoleCommand)]
+#[command(
+    name = "step",
+    about = "Runs simulation steps, one per frame, pausing gameplay time first"
+)]
+struct StepCommand {
+    #[arg(default_value_t = 1)]
+    steps: u32,
+}
+
+#[derive(Parser, ConsoleCommand)]
+#[command(
+    name = "timescale",
+    about = "Prints or sets speed of gameplay time, 1 is normal speed"
+)]
+struct TimeScaleCommand {
+    scale: Option<f32>,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum GizmoOverlay {
+    Navigation,
+    Pickup,
+    Npc,
+    Camera,
+    Storage,
+    Health,
+}
+
+#[derive(Parser, ConsoleCommand)]
+#[command(
+    name = "gizmos",
+    about = "Toggles a debug overlay, lists enabled overlays without arguments"
+)]
+struct GizmosCommand {
+    overlay: Option<GizmoOverlay>,
+}
+
+#[derive(Parser, ConsoleCommand)]
+#[command(
+    name = "profiler",
+    about = "Toggles performance HUD with frame times, entity counts and system timings"
+)]
+struct ProfilerCommand;
+
+/// Keyframes added with camera-key.
+#[derive(Resource, Default)]
+struct CameraTrackDraft(Vec<CameraKeyframe>);
+
+fn parse_action_binding(
+    action: &str,
+    binding: &str,
+) -> Result<(InputAction, InputBinding), String> {
+    let action =
+        InputAction::from_str(action).map_err(|_| format!("Cannot parse action '{}'", action))?;
+    let binding = InputBinding::parse(binding)?;
+    Ok((action, binding))
+}
+
+fn list_bindings(mut command: ConsoleCommand<ListBindingsCommand>, map: Res<InputMap>) {
+    let Some(Ok(ListBindingsCommand)) = command.take() else {
+        return;
+    };
+
+    for (action, bindings) in map.iter() {
+        let bindings = bindings.iter().map(|b| b.to_string()).collect::<Vec<_>>();
+        command.reply(format!("{} - {}", action, bindings.join(", ")));
+    }
+}
+
+fn bind(mut command: ConsoleCommand<BindCommand>, mut map: ResMut<InputMap>) {
+    let Some(Ok(BindCommand { action, binding })) = command.take() else {
+        return;
+    };
+
+    let (action, binding) = match parse_action_binding(&action, &binding) {
+        Ok(parsed) => parsed,
+        Err(error) => {
+            command.reply(error);
+            return;
+        }
+    };
+
+    match map.bind(action, binding) {
+        Ok(()) => command.reply(format!("{} is now bound to {}", action, binding)),
+        Err(conflict) => command.reply(format!(
+            "Cannot bind {} to {}, it is already used by {}",
+            action, binding, conflict
+        )),
+    }
+}
+
+fn unbind(mut command: ConsoleCommand<UnbindCommand>, mut map: ResMut<InputMap>) {
+    let Some(Ok(UnbindCommand { action, binding })) = command.take() else {
+        return;
+    };
+
+    match parse_action_binding(&action, &binding) {
+        Ok((action, binding)) => {
+            map.unbind(action, binding);
+            command.reply(format!("{} is no longer bound to {}", action, binding));
+        }
+        Err(error) => command.reply(error),
+    }
+}
+
+fn rebind(mut command: ConsoleCommand<RebindCommand>, mut pending: ResMut<PendingRebind>) {
+    let Some(Ok(RebindCommand { action })) = command.take() else {
+        return;
+    };
+
+    let Ok(action) = InputAction::from_str(&action) else {
+        command.reply(format!("Cannot parse action '{}'", action));
+        return;
+    };
+
+    pending.0 = Some(action);
+    command.reply(format!(
+        "Press key or mouse button to bind {}, Escape cancels",
+        action
+    ));
+}
+
+fn input_context(
+    mut command: ConsoleCommand<InputContextCommand>,
+    mut contexts: ResMut<InputContexts>,
+) {
+    let Some(Ok(InputContextCommand { push, remove })) = command.take() else {
+        return;
+    };
+
+    for (context, push) in [(push, true), (remove, false)] {
+        let Some(context) = context else {
+            continue;
+        };
+
+        let Ok(context) = InputContext::from_str(&context) else {
+            command.reply(format!("Cannot parse input context '{}'", context));
+            return;
+        };
+
+        if push {
+            contexts.push(context);
+        } else {
+            contexts.remove(context);
+        }
+    }
+
+    let stack = contexts.iter().map(|c| c.to_string()).collect::<Vec<_>>();
+    command.reply(format!("Input contexts: {}", stack.join(" > ")));
+}
+
+fn sync_console_input_context(console: Res<ConsoleOpen>, mut contexts: ResMut<InputContexts>) {
+    if console.open {
+        contexts.push(InputContext::Console);
+    } else {
+        contexts.remove(InputContext::Console);
+    }
+}
+
+fn camera_mode(
+    mut command: ConsoleCommand<CameraModeCommand>,
+    camera: Single<(&Transform, &mut GameCamera)>,
+    draft: Res<CameraTrackDraft>,
+) {
+    let Some(Ok(CameraModeCommand { mode, looping })) = command.take() else {
+        return;
+    };
+
+    let (transform, mut camera) = camera.into_inner();
+    let mode = match mode {
+        CameraModeName::Follow => CameraMode::Follow,
+        CameraModeName::FreeFly => CameraMode::FreeFly {
+            translation: transform.translation,
+        },
+        CameraModeName::Fixed => CameraMode::Fixed {
+            translation: transform.translation,
+            look_at: transform.translation + *transform.forward(),
+        },
+        CameraModeName::Track if draft.0.len() < 2 => {
+            command.reply("Track needs at least 2 keyframes, add them with camera-key");
+            return;
+        }
+        CameraModeName::Track => CameraMode::Track(CameraTrack::new(draft.0.clone(), looping)),
+    };
+
+    camera.set_mode(mode);
+    command.reply(format!("Camera mode: {:?}", camera.mode()));
+}
+
+fn camera_target(
+    mut command: ConsoleCommand<CameraTargetCommand>,
+    mut camera: Single<&mut GameCamera>,
+    mut selectors: Selectors,
+    entities: Query<(), With<Transform>>,
+) {
+    let Some(Ok(CameraTargetCommand { entity })) = command.take() else {
+        return;
+    };
+
+    let Some(selector) = entity else {
+        camera.set_target(None);
+        command.reply("Camera follows the default target");
+        return;
+    };
+
+    match selectors.select_one(&selector) {
+        Ok(target) if entities.contains(target) => {
+            camera.set_target(Some(target));
+            command.reply(format!("Camera follows {}", target));
+        }
+        Ok(target) => command.reply(format!("{} has no position to follow", target)),
+        Err(error) => command.reply(error),
+    }
+}
+
+fn camera_key(
+    mut command: ConsoleCommand<CameraKeyCommand>,
+    camera: Single<&Transform, With<GameCamera>>,
+    mut draft: ResMut<CameraTrackDraft>,
+) {
+    let Some(Ok(CameraKeyCommand { time, clear })) = command.take() else {
+        return;
+    };
+
+    if clear {
+        draft.0.clear();
+        command.reply("Camera track keyframes removed");
+        return;
+    }
+
+    // without explicit time keyframes are one second apart
+    let time = time.unwrap_or_else(|| draft.0.last().map_or(0.0, |k| k.time + 1.0));
+    draft.0.push(CameraKeyframe::from_transform(time, &camera));
+    command.reply(format!("Added keyframe {} at {}s", draft.0.len(), time));
+}
+
+fn get_component(
+    mut command: ConsoleCommand<GetCommand>,
+    mut selectors: Selectors,
+    mut commands: Commands,
+) {
+    let Some(Ok(GetCommand { entity, path })) = command.take() else {
+        return;
+    };
+
+    let entities = match selectors.select(&entity) {
+        Ok(entities) => entities,
+        Err(error) => {
+            command.reply(error);
+            return;
+        }
+    };
+
+    // reflection needs the whole world, so the work is done when commands are applied
+    commands.queue(move |world: &mut World| {
+        for entity in entities {
+            let reply = match &path {
+                Some(path) => get_reflected(world, entity, path),
+                None => list_reflected(world, entity),
+            };
+            world.send_event(PrintConsoleLine::new(reply.unwrap_or_else(|e| e)));
+        }
+    });
+}
+
+fn set_component(
+    mut command: ConsoleCommand<SetCommand>,
+    mut selectors: Selectors,
+    mut commands: Commands,
+) {
+    let Some(Ok(SetCommand {
+        entity,
+        path,
+        value,
+    })) = command.take()
+    else {
+        return;
+    };
+
+    let entities = match selectors.select(&entity) {
+        Ok(entities) => entities,
+        Err(error) => {
+            command.reply(error);
+            return;
+        }
+    };
+
+    let value = value.join(" ");
+    commands.queue(move |world: &mut World| {
+        for entity in entities {
+            let reply = set_reflected(world, entity, &path, &value);
+            world.send_event(PrintConsoleLine::new(reply.unwrap_or_else(|e| e)));
+        }
+    });
+}
+
+fn list_reflected(world: &World, entity: Entity) -> Result<String, String> {
+    let registry = world.resource::<AppTypeRegistry>().read();
+    let entity_ref = world
+        .get_entity(entity)
+        .map_err(|_| format!("Cannot find entity '{}'", entity))?;
+
+    let mut names = entity_ref
+        .archetype()
+        .components()
+        .filter_map(|id| world.components().get_info(id)?.type_id())
+        .filter_map(|type_id| registry.get(type_id))
+        .filter(|registration| registration.data::<ReflectComponent>().is_some())
+        .map(|registration| registration.type_info().type_path_table().short_path())
+        .collect::<Vec<_>>();
+    names.sort();
+    Ok(format!("{}: {}", entity, names.join(", ")))
+}
+
+fn get_reflected(world: &World, entity: Entity, path: &str) -> Result<String, String> {
+    let registry = world.resource::<AppTypeRegistry>().read();
+    let (name, field) = split_component_path(path);
+    let component = reflect_component(&registry, name)?;
+    let entity_ref = world
+        .get_entity(entity)
+        .map_err(|_| format!("Cannot find entity '{}'", entity))?;
+
+    let value = component
+        .reflect(entity_ref)
+        .ok_or_else(|| format!("{} has no {}", entity, name))?;
+    let value = match field {
+        "" => value.as_partial_reflect(),
+        field => value
+            .reflect_path(field)
+            .map_err(|e| format!("Cannot access '{}': {}", path, e))?,
+    };
+    Ok(format!(
+        "{} {} = {}",
+        entity,
+        path,
+        serialize_reflected(value, &registry)
+    ))
+}
+
+fn set_reflected(
+    world: &mut World,
+    entity: Entity,
+    path: &str,
+    input: &str,
+) -> Result<String, String> {
+    let registry = world.resource::<AppTypeRegistry>().clone();
+    let registry = registry.read();
+    let (name, field) = split_component_path(path);
+    let component = reflect_component(&registry, name)?;
+    let entity_mut = world
+        .get_entity_mut(entity)
+        .map_err(|_| format!("Cannot find entity '{}'", entity))?;
+
+    let mut value = component
+        .reflect_mut(entity_mut)
+        .ok_or_else(|| format!("{} has no {}", entity, name))?;
+    let target = match field {
+        "" => value.as_partial_reflect_mut(),
+        field => value
+            .reflect_path_mut(field)
+            .map_err(|e| format!("Cannot access '{}': {}", path, e))?,
+    };
+
+    let type_info = target
+        .get_represented_type_info()
+        .ok_or_else(|| format!("Type of '{}' is unknown", path))?;
+    let registration = registry
+        .get(type_info.type_id())
+        .ok_or_else(|| format!("{} is not registered", type_info.type_path()))?;
+    let parsed = ron::Deserializer::from_str(input)
+        .map_err(|e| e.to_string())
+        .and_then(|mut deserializer| {
+            TypedReflectDeserializer::new(registration, &registry)
+                .deserialize(&mut deserializer)
+                .map_err(|e| e.to_string())
+        })
+        .map_err(|e| {
+            format!(
+                "Cannot parse '{}' as {}: {}",
+                input,
+                type_info.type_path(),
+                e
+            )
+        })?;
+
+    target
+        .try_apply(parsed.as_ref())
+        .map_err(|e| format!("Cannot set '{}': {}", path, e))?;
+    Ok(format!(
+        "{} {} = {}",
+        entity,
+        path,
+        serialize_reflected(target, &registry)
+    ))
+}
+
+/// Finds reflected component by its short (like `Health`) or full type path.
+pub(super) fn reflect_component<'a>(
+    registry: &'a TypeRegistry,
+    name: &str,
+) -> Result<&'a ReflectComponent, String> {
+    registry
+        .get_with_short_type_path(name)
+        .or_else(|| registry.get_with_type_path(name))
+        .ok_or_else(|| format!("Cannot find type '{}'", name))?
+        .data::<ReflectComponent>()
+        .ok_or_else(|| format!("{} is not a reflected component", name))
+}
+
+/// Splits `Health.current` into the component name and its field path.
+pub(super) fn split_component_path(path: &str) -> (&str, &str) {
+    match path.find(['.', '[']) {
+        Some(index) => path.split_at(index),
+        None => (path, ""),
+    }
+}
+
+fn serialize_reflected(value: &dyn PartialReflect, registry: &TypeRegistry) -> String {
+    ron::to_string(&TypedReflectSerializer::new(value, registry))
+        .unwrap_or_else(|_| format!("{:?}", value))
+}
+
+fn give<T: PrototypeId>(
+    mut command: ConsoleCommand<GiveCommand>,
+    mut commands: Commands,
+    registry: Res<PrototypeRegistry<T>>,
+    mut selectors: Selectors,
+    storages: Query<(), With<ItemStorage>>,
+) {
+    let Some(Ok(GiveCommand { id, count, target })) = command.take() else {
+        return;
+    };
+
+    let id = match parse_id(&id, registry.ids()) {
+        Ok(id) => id,
+        Err(error) => {
+            command.reply(error);
+            return;
+        }
+    };
+
+    let targets = match selectors.select(&target) {
+        Ok(targets) => targets,
+        Err(error) => {
+            command.reply(error);
+            return;
+        }
+    };
+
+    for storage in targets {
+        if !storages.contains(storage) {
+            command.reply(format!("{} has no item storage", storage));
+            continue;
+        }
+
+        for _ in 0..count {
+            let item = registry.spawn(id, &mut commands);
+            commands.queue(InsertItemCommand { storage, item });
+        }
+        command.reply(format!("Gave {} {} to {}", count, id, storage));
+    }
+}
+
+fn teleport(
+    mut command: ConsoleCommand<TeleportCommand>,
+    mut selectors: Selectors,
+    transforms: Query<&GlobalTransform>,
+    mut entities: Query<(
+        &mut Transform,
+        Option<&mut CharacterController>,
+        Option<&mut NavPath>,
+        Option<&mut PlayerOrder>,
+    )>,
+) {
+    let Some(Ok(TeleportCommand { target, position })) = command.take() else {
+        return;
+    };
+
+    let translation = match position.resolve(&mut selectors, &transforms) {
+        Ok(translation) => translation,
+        Err(error) => {
+            command.reply(format!("Cannot teleport to entity position. {}", error));
+            return;
+        }
+    };
+
+    let targets = match selectors.select(&target) {
+        Ok(targets) => targets,
+        Err(error) => {
+            command.reply(error);
+            return;
+        }
+    };
+
+    for target in targets {
+        let Ok((mut transform, controller, path, order)) = entities.get_mut(target) else {
+            command.reply(format!("{} has no position", target));
+            continue;
+        };
+
+        transform.translation = translation;
+        // movement from before the teleport would pull it back
+        if let Some(mut controller) = controller {
+            controller.velocity = Vec3::ZERO;
+            controller.grounded = false;
+        }
+        if let Some(mut path) = path {
+            path.clear();
+        }
+        if let Some(mut order) = order {
+            *order = PlayerOrder::None;
+        }
+        command.reply(format!("{} has been teleported to {}", target, translation));
+    }
+}
+
+fn heal(
+    mut command: ConsoleCommand<HealCommand>,
+    mut selectors: Selectors,
+    mut healths: Query<&mut Health>,
+) {
+    let Some(Ok(HealCommand { target, amount })) = command.take() else {
+        return;
+    };
+
+    let targets = match selectors.select(&target) {
+        Ok(targets) => targets,
+        Err(error) => {
+            command.reply(error);
+            return;
+        }
+    };
+
+    for target in targets {
+        let Ok(mut health) = healths.get_mut(target) else {
+            command.reply(format!("{} has no health", target));
+            continue;
+        };
+
+        let amount = amount.unwrap_or(health.max);
+        health.heal(amount);
+        command.reply(format!(
+            "{} health: {}/{}",
+            target, health.current, health.max
+        ));
+    }
+}
+
+fn damage(
+    mut command: ConsoleCommand<DamageCommand>,
+    mut selectors: Selectors,
+    mut damage: Damage,
+) {
+    let Some(Ok(DamageCommand { target, amount })) = command.take() else {
+        return;
+    };
+
+    let targets = match selectors.select(&target) {
+        Ok(targets) => targets,
+        Err(error) => {
+            command.reply(error);
+            return;
+        }
+    };
+
+    for target in targets {
+        match damage.deal(None, target, amount) {
+            Ok(health) if health.is_dead() => {
+                command.reply(format!("{} has been killed", target));
+            }
+            Ok(health) => command.reply(format!(
+                "{} health: {}/{}",
+                target, health.current, health.max
+            )),
+            Err(error) => command.reply(error),
+        }
+    }
+}
+
+fn kill(mut command: ConsoleCommand<KillCommand>, mut selectors: Selectors, mut damage: Damage) {
+    let Some(Ok(KillCommand { target })) = command.take() else {
+        return;
+    };
+
+    let targets = match selectors.select(&target) {
+        Ok(targets) => targets,
+        Err(error) => {
+            command.reply(error);
+            return;
+        }
+    };
+
+    for target in targets {
+        match damage.kill(None, target) {
+            Ok(_) => command.reply(format!("{} has been killed", target)),
+            Err(error) => command.reply(error),
+        }
+    }
+}
+
+fn god(
+    mut command: ConsoleCommand<GodCommand>,
+    mut commands: Commands,
+    mut selectors: Selectors,
+    characters: Query<Has<Invulnerable>, With<Health>>,
+) {
+    let Some(Ok(GodCommand { target })) = command.take() else {
+        return;
+    };
+
+    let targets = match selectors.select(&target) {
+        Ok(targets) => targets,
+        Err(error) => {
+            command.reply(error);
+            return;
+        }
+    };
+
+    for target in targets {
+        match characters.get(target) {
+            Ok(true) => {
+                commands.entity(target).remove::<Invulnerable>();
+                command.reply(format!("God mode disabled for {}", target));
+            }
+            Ok(false) => {
+                commands.entity(target).insert(Invulnerable);
+                command.reply(format!("God mode enabled for {}", target));
+            }
+            Err(_) => command.reply(format!("{} has no health", target)),
+        }
+    }
+}
+
+fn noclip(
+    mut command: ConsoleCommand<NoclipCommand>,
+    mut selectors: Selectors,
+    mut controllers: Query<&mut CharacterController>,
+) {
+    let Some(Ok(NoclipCommand { target })) = command.take() else {
+        return;
+    };
+
+    let targets = match selectors.select(&target) {
+        Ok(targets) => targets,
+        Err(error) => {
+            command.reply(error);
+            return;
+        }
+    };
+
+    for target in targets {
+        let Ok(mut controller) = controllers.get_mut(target) else {
+            command.reply(format!("{} has no character controller", target));
+            continue;
+        };
+
+        controller.noclip = !controller.noclip;
+        controller.velocity = Vec3::ZERO;
+        command.reply(format!(
+            "Noclip {} for {}",
+            if controller.noclip {
+                "enabled"
+            } else {
+                "disabled"
+            },
+            target
+        ));
+    }
+}
+
+fn exec(mut command: ConsoleCommand<ExecCommand>, mut runner: ResMut<ScriptRunner>) {
+    let Some(Ok(ExecCommand { file })) = command.take() else {
+        return;
+    };
+
+    match runner.exec(&file) {
+        Ok(()) => command.reply(format!("Running '{}'", file.display())),
+        Err(error) => command.reply(error),
+    }
+}
+
+fn pause(
+    mut command: ConsoleCommand<PauseCommand>,
+    mut control: ResMut<TimeControl>,
+    mut time: ResMut<Time<Virtual>>,
+) {
+    let Some(Ok(PauseCommand)) = command.take() else {
+        return;
+    };
+
+    control.toggle_pause(&mut time);
+    command.reply(if time.is_paused() {
+        "Time paused, use step to advance it"
+    } else {
+        "Time resumed"
+    });
+}
+
+fn step(
+    mut command: ConsoleCommand<StepCommand>,
+    mut control: ResMut<TimeControl>,
+    mut time: ResMut<Time<Virtual>>,
+) {
+    let Some(Ok(StepCommand { steps })) = command.take() else {
+        return;
+    };
+
+    control.step(&mut time, steps);
+    command.reply(format!("Running {} simulation steps", steps));
+}
+
+fn time_scale(
+    mut command: ConsoleCommand<TimeScaleCommand>,
+    mut control: ResMut<TimeControl>,
+    mut time: ResMut<Time<Virtual>>,
+) {
+    let Some(Ok(TimeScaleCommand { scale })) = command.take() else {
+        return;
+    };
+
+    match scale {
+        Some(scale) => {
+            let scale = control.set_scale(&mut time, scale);
+            command.reply(format!("Time scale set to {}", scale));
+        }
+        None => command.reply(format!("Time scale is {}", time.relative_speed())),
+    }
+}
+
+fn gizmos(
+    mut command: ConsoleCommand<GizmosCommand>,
+    mut gizmos: ResMut<DebugGizmos>,
+    mut navigation: ResMut<NavigationDebug>,
+) {
+    let Some(Ok(GizmosCommand { overlay })) = command.take() else {
+        return;
+    };
+
+    let gizmos = gizmos.as_mut();
+    let mut overlays = [
+        (GizmoOverlay::Navigation, &mut navigation.enabled),
+        (GizmoOverlay::Pickup, &mut gizmos.pickup_ranges),
+        (GizmoOverlay::Npc, &mut gizmos.npc_targets),
+        (GizmoOverlay::Camera, &mut gizmos.camera),
+        (GizmoOverlay::Storage, &mut gizmos.storage),
+        (GizmoOverlay::Health, &mut gizmos.health_bars),
+    ];
+
+    for (name, enabled) in overlays.iter_mut() {
+        let name = name.to_possible_value().expect("overlays are not skipped");
+        match overlay {
+            Some(overlay) if overlay.to_possible_value().as_ref() == Some(&name) => {
+                **enabled = !**enabled;
+                let state = if **enabled { "enabled" } else { "disabled" };
+                command.reply(format!("{} gizmos {}", name.get_name(), state));
+            }
+            None => {
+                let state = if **enabled { "on" } else { "off" };
+                command.reply(format!("{} - {}", name.get_name(), state));
+            }
+            Some(_) => {}
+        }
+    }
+}
+
+fn profiler(mut command: ConsoleCommand<ProfilerCommand>, hud: Option<ResMut<ProfilerHud>>) {
+    let Some(Ok(ProfilerCommand)) = command.take() else {
+        return;
+    };
+
+    // there is nothing to draw the HUD into without a window
+    let Some(mut hud) = hud else {
+        command.reply("Profiler is not available headless");
+        return;
+    };
+
+    hud.visible = !hud.visible;
+    command.reply(if hud.visible {
+        "Profiler shown"
+    } else {
+        "Profiler hidden"
+    });
+}
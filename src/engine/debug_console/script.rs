@@ -0,0 +1,361 @@
+use std::collections::VecDeque;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use bevy_console::{ConsoleCommandEntered, ConsoleConfiguration, PrintConsoleLine};
+use shlex::Shlex;
+
+/// Scripts started from other scripts, or aliases expanded from other aliases,
+/// deeper than this are most likely recursive.
+const MAX_SCRIPT_DEPTH: usize = 16;
+
+/// Runs console commands read from files, one per line:
+/// - empty lines and lines starting with `#` are skipped,
+/// - `var name value` defines a variable, `$name` or `${name}` is replaced by its value,
+/// - `alias name command; command` defines a command running the other ones,
+///   variables in them are replaced when the alias runs, not when it is defined,
+/// - `wait seconds` delays the rest of the script, in real time, so it works while paused.
+///
+/// Only one command is sent per frame, so every command sees effects of the previous ones.
+#[derive(Resource, Default)]
+pub struct ScriptRunner {
+    /// Running scripts, the last one runs first, so `exec` inside a script finishes
+    /// before the rest of it.
+    scripts: Vec<Script>,
+    variables: HashMap<String, String>,
+    aliases: HashMap<String, Vec<String>>,
+    wait: f32,
+}
+
+struct Script {
+    path: PathBuf,
+    /// Remaining lines, commands of expanded aliases are put in front of them.
+    lines: VecDeque<ScriptLine>,
+}
+
+struct ScriptLine {
+    /// Used in error messages, expanded alias commands have the number of the alias line.
+    number: usize,
+    /// How many aliases were expanded to get this line.
+    depth: usize,
+    text: String,
+}
+
+impl ScriptRunner {
+    pub fn exec(&mut self, path: &Path) -> Result<(), String> {
+        if self.scripts.len() >= MAX_SCRIPT_DEPTH {
+            return Err(format!(
+                "Cannot run '{}', scripts are nested too deep",
+                path.display()
+            ));
+        }
+
+        let content = fs::read_to_string(path)
+            .map_err(|e| format!("Cannot read '{}': {}", path.display(), e))?;
+        let lines = content
+            .lines()
+            .enumerate()
+            .map(|(index, line)| ScriptLine {
+                number: index + 1,
+                depth: 0,
+                text: line.trim().to_string(),
+            })
+            .filter(|line| !line.text.is_empty() && !line.text.starts_with('#'))
+            .collect();
+
+        self.scripts.push(Script {
+            path: path.to_path_buf(),
+            lines,
+        });
+        Ok(())
+    }
+
+    /// Next command to send, directives are handled on the way.
+    fn next_command(
+        &mut self,
+        config: &ConsoleConfiguration,
+        output: &mut EventWriter<PrintConsoleLine>,
+    ) -> Option<ConsoleCommandEntered> {
+        while self.wait <= 0.0 {
+            let script = self.scripts.last_mut()?;
+            let Some(line) = script.lines.pop_front() else {
+                self.scripts.pop();
+                continue;
+            };
+
+            let location = format!("{}:{}", script.path.display(), line.number);
+            match self.run_line(&line, config) {
+                Ok(Some(command)) => {
+                    output.send(PrintConsoleLine::new(format!(
+                        "{}{}",
+                        config.symbol, line.text
+                    )));
+                    return Some(command);
+                }
+                Ok(None) => {}
+                Err(error) => {
+                    output.send(PrintConsoleLine::new(format!("{}: {}", location, error)));
+                }
+            }
+        }
+        None
+    }
+
+    fn run_line(
+        &mut self,
+        script_line: &ScriptLine,
+        config: &ConsoleConfiguration,
+    ) -> Result<Option<ConsoleCommandEntered>, String> {
+        let line = if script_line.text.split_whitespace().next() == Some("alias") {
+            script_line.text.clone()
+        } else {
+            self.substitute(&script_line.text)?
+        };
+        let (name, rest) = line
+            .split_once(char::is_whitespace)
+            .map_or((line.as_str(), ""), |(name, rest)| (name, rest.trim()));
+
+        match name {
+            "var" => {
+                let Some((variable, value)) = rest.split_once(char::is_whitespace) else {
+                    return Err("Expected var <name> <value>".to_string());
+                };
+                self.variables
+                    .insert(variable.to_string(), value.trim().to_string());
+            }
+            "alias" => {
+                let Some((alias, body)) = rest.split_once(char::is_whitespace) else {
+                    return Err("Expected alias <name> <command>; <command>...".to_string());
+                };
+                let commands = body
+                    .split(';')
+                    .map(|command| command.trim().to_string())
+                    .filter(|command| !command.is_empty())
+                    .collect();
+                self.aliases.insert(alias.to_string(), commands);
+            }
+            "wait" => {
+                self.wait = rest
+                    .parse::<f32>()
+                    .map_err(|_| format!("Cannot parse wait time '{}'", rest))?;
+            }
+            alias if self.aliases.contains_key(alias) => {
+                if script_line.depth >= MAX_SCRIPT_DEPTH {
+                    return Err(format!(
+                        "Cannot expand alias '{}', aliases are nested too deep",
+                        alias
+                    ));
+                }
+
+                // alias commands run in place of it, before the rest of the script
+                let script = self.scripts.last_mut().expect("line comes from a script");
+                for command in self.aliases[alias].iter().rev() {
+                    script.lines.push_front(ScriptLine {
+                        number: script_line.number,
+                        depth: script_line.depth + 1,
+                        text: command.clone(),
+                    });
+                }
+            }
+            _ => {
+                let mut args = Shlex::new(&line).collect::<Vec<_>>();
+                if args.is_empty() {
+                    return Ok(None);
+                }
+
+                let command_name = args.remove(0);
+                if !config.commands.contains_key(command_name.as_str()) {
+                    return Err(format!("Unknown command '{}'", command_name));
+                }
+                return Ok(Some(ConsoleCommandEntered { command_name, args }));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Replaces `$name` and `${name}` with values of variables.
+    fn substitute(&self, line: &str) -> Result<String, String> {
+        let mut result = String::with_capacity(line.len());
+        let mut rest = line;
+        while let Some(start) = rest.find('$') {
+            result.push_str(&rest[..start]);
+            rest = &rest[start + 1..];
+
+            let (name, length) = match rest.strip_prefix('{') {
+                Some(braced) => {
+                    let end = braced
+                        .find('}')
+                        .ok_or_else(|| "Missing '}' after variable name".to_string())?;
+                    (&braced[..end], end + 2)
+                }
+                None => {
+                    let end = rest
+                        .find(|c: char| !c.is_alphanumeric() && c != '_')
+                        .unwrap_or(rest.len());
+                    (&rest[..end], end)
+                }
+            };
+
+            let value = self
+                .variables
+                .get(name)
+                .ok_or_else(|| format!("Unknown variable '{}'", name))?;
+            result.push_str(value);
+            rest = &rest[length..];
+        }
+        result.push_str(rest);
+        Ok(result)
+    }
+}
+
+pub(super) fn run_scripts(
+    mut runner: ResMut<ScriptRunner>,
+    config: Res<ConsoleConfiguration>,
+    mut commands: EventWriter<ConsoleCommandEntered>,
+    mut output: EventWriter<PrintConsoleLine>,
+    time: Res<Time<Real>>,
+) {
+    if runner.scripts.is_empty() {
+        return;
+    }
+
+    runner.wait -= time.delta_secs();
+    if let Some(command) = runner.next_command(&config, &mut output) {
+        commands.send(command);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::system::SystemState;
+
+    use super::*;
+
+    /// Commands sent by the script and lines printed to the console, including errors.
+    fn run(name: &str, script: &str) -> (Vec<String>, Vec<String>) {
+        let directory = std::env::temp_dir().join("andromeda-tests");
+        fs::create_dir_all(&directory).unwrap();
+        let path = directory.join(name);
+        fs::write(&path, script).unwrap();
+
+        let mut config = ConsoleConfiguration::default();
+        config.commands.insert("say", clap::Command::new("say"));
+        let mut runner = ScriptRunner::default();
+        runner.exec(&path).unwrap();
+
+        let mut world = World::new();
+        world.init_resource::<Events<PrintConsoleLine>>();
+        let mut state = SystemState::<EventWriter<PrintConsoleLine>>::new(&mut world);
+        let mut output = state.get_mut(&mut world);
+        let mut commands = Vec::new();
+        while let Some(command) = runner.next_command(&config, &mut output) {
+            commands.push(format!(
+                "{} {}",
+                command.command_name,
+                command.args.join(" ")
+            ));
+        }
+
+        let lines = world
+            .resource::<Events<PrintConsoleLine>>()
+            .iter_current_update_events()
+            .map(|line| line.line.clone())
+            .collect();
+        (commands, lines)
+    }
+
+    #[test]
+    fn substitutes_variables() {
+        let runner = ScriptRunner {
+            variables: HashMap::from_iter([
+                ("x".to_string(), "1".to_string()),
+                ("long_name".to_string(), "2".to_string()),
+            ]),
+            ..default()
+        };
+
+        let cases = [
+            ("say $x", Ok("say 1")),
+            ("say (-$x,$long_name)", Ok("say (-1,2)")),
+            ("say ${x}0", Ok("say 10")),
+            ("say $x$x", Ok("say 11")),
+            ("say no variables", Ok("say no variables")),
+            ("say $y", Err("Unknown variable 'y'")),
+            ("say ${x", Err("Missing '}' after variable name")),
+        ];
+
+        for (line, expected) in cases {
+            assert_eq!(
+                runner.substitute(line).as_deref(),
+                expected.map_err(str::to_string).as_deref(),
+                "{}",
+                line
+            );
+        }
+    }
+
+    #[test]
+    fn skips_comments_and_empty_lines() {
+        let (commands, _) = run("comments.cfg", "# comment\n\n   # indented\nsay hi\n");
+
+        assert_eq!(commands, ["say hi"]);
+    }
+
+    #[test]
+    fn expands_aliases_in_place() {
+        let script = "\
+            alias greet say hello; say $who\n\
+            alias twice greet; greet\n\
+            var who world\n\
+            twice\n\
+            var who there\n\
+            greet\n\
+            say done\n";
+        let (commands, _) = run("aliases.cfg", script);
+
+        assert_eq!(commands, [
+            "say hello",
+            "say world",
+            "say hello",
+            "say world",
+            "say hello",
+            "say there",
+            "say done",
+        ]);
+    }
+
+    #[test]
+    fn stops_recursive_alias() {
+        let (commands, lines) = run("recursive.cfg", "alias loop say x; loop\nloop\nsay after\n");
+
+        assert_eq!(commands.len(), MAX_SCRIPT_DEPTH + 1);
+        assert_eq!(commands.last().unwrap(), "say after");
+        assert!(
+            lines
+                .iter()
+                .any(|line| line
+                    .ends_with("Cannot expand alias 'loop', aliases are nested too deep")),
+            "{:?}",
+            lines
+        );
+    }
+
+    #[test]
+    fn reports_errors_with_line_numbers() {
+        let (commands, lines) = run("errors.cfg", "say $missing\nunknown\nsay ok\n");
+
+        assert_eq!(commands, ["say ok"]);
+        assert!(
+            lines
+                .iter()
+                .any(|line| line.ends_with("errors.cfg:1: Unknown variable 'missing'"))
+        );
+        assert!(
+            lines
+                .iter()
+                .any(|line| line.ends_with("errors.cfg:2: Unknown command 'unknown'"))
+        );
+    }
+}
@@ -0,0 +1,328 @@
+use std::ops::RangeInclusive;
+use std::str::FromStr;
+
+use bevy::ecs::entity::Entities;
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use derive_more::derive::FromStr;
+use rand::seq::SliceRandom;
+
+use crate::engine::character::player::Player;
+use crate::engine::inspector::InspectorSelection;
+use crate::engine::prototype::PrototypeName;
+use crate::engine::random::GameRng;
+
+/// Console argument addressing entities:
+/// - `12v1` - entity in the format it is displayed,
+/// - `@p` - the player,
+/// - `@s` - entities selected in the inspector,
+/// - `@e` - entities spawned from prototypes (characters and items), filtered with
+///   arguments like `@e[type=Enemy,distance=..10,sort=nearest,limit=5]`.
+#[derive(Clone, PartialEq, Debug)]
+pub enum EntitySelector {
+    Entity(Entity),
+    Player,
+    Selected,
+    All(SelectorFilter),
+}
+
+#[derive(Clone, Default, PartialEq, Debug)]
+pub struct SelectorFilter {
+    /// Prototype id, like `Enemy`.
+    kind: Option<Matcher>,
+    name: Option<Matcher>,
+    /// Distance from the player.
+    distance: Option<RangeInclusive<f32>>,
+    limit: Option<usize>,
+    sort: SelectorSort,
+}
+
+/// Text compared without case, `!` in front negates it, like `type=!Enemy`.
+#[derive(Clone, PartialEq, Debug)]
+struct Matcher {
+    value: String,
+    negated: bool,
+}
+
+impl Matcher {
+    fn parse(input: &str) -> Self {
+        match input.strip_prefix('!') {
+            Some(value) => Self {
+                value: value.to_string(),
+                negated: true,
+            },
+            None => Self {
+                value: input.to_string(),
+                negated: false,
+            },
+        }
+    }
+
+    fn matches(&self, text: Option<&str>) -> bool {
+        text.is_some_and(|text| text.eq_ignore_ascii_case(&self.value)) != self.negated
+    }
+}
+
+#[derive(Clone, Copy, Default, PartialEq, Eq, FromStr, Debug)]
+enum SelectorSort {
+    /// In order of entity ids.
+    #[default]
+    Arbitrary,
+    Nearest,
+    Furthest,
+    Random,
+}
+
+impl FromStr for EntitySelector {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let input = input.trim();
+        let (head, arguments) = match input.split_once('[') {
+            Some((head, arguments)) => {
+                let Some(arguments) = arguments.strip_suffix(']') else {
+                    return Err(format!("Missing ']' at the end of '{}'", input));
+                };
+                (head, Some(arguments))
+            }
+            None => (input, None),
+        };
+
+        let mut filter = match (head, arguments) {
+            ("@p", None) => return Ok(Self::Player),
+            ("@s", None) => return Ok(Self::Selected),
+            ("@e", _) => SelectorFilter::default(),
+            ("@p" | "@s", Some(_)) => {
+                return Err(format!("Only @e accepts arguments, not {}", head));
+            }
+            (_, None) => {
+                return parse_entity(input).map(Self::Entity).ok_or_else(|| {
+                    format!(
+                        "Cannot parse entity '{}'. Supported values are 12v1, @p, @s, @e[...].",
+                        input
+                    )
+                });
+            }
+            (_, Some(_)) => return Err(format!("Unknown selector '{}'", head)),
+        };
+
+        let arguments = arguments.unwrap_or_default().split(',');
+        for argument in arguments.map(str::trim).filter(|a| !a.is_empty()) {
+            let Some((key, value)) = argument.split_once('=') else {
+                return Err(format!("Expected key=value, found '{}'", argument));
+            };
+
+            let value = value.trim();
+            match key.trim() {
+                "type" => filter.kind = Some(Matcher::parse(value)),
+                "name" => filter.name = Some(Matcher::parse(value)),
+                "distance" => filter.distance = Some(parse_range(value)?),
+                "limit" => match value.parse::<usize>() {
+                    Ok(limit) if limit > 0 => filter.limit = Some(limit),
+                    _ => return Err(format!("Cannot parse limit '{}'", value)),
+                },
+                "sort" => {
+                    filter.sort = SelectorSort::from_str(value).map_err(|_| {
+                        format!(
+                            "Cannot parse sort '{}'. Supported values are nearest, furthest, random, arbitrary.",
+                            value
+                        )
+                    })?;
+                }
+                key => {
+                    return Err(format!(
+                        "Unknown selector argument '{}'. Supported arguments are type, name, distance, limit, sort.",
+                        key
+                    ));
+                }
+            }
+        }
+        Ok(Self::All(filter))
+    }
+}
+
+/// Parses `5`, `..10`, `5..` or `5..10`, bounds are inclusive.
+fn parse_range(input: &str) -> Result<RangeInclusive<f32>, String> {
+    let parse = |bound: &str, default: f32| match bound.trim() {
+        "" => Ok(default),
+        bound => bound
+            .parse::<f32>()
+            .map_err(|_| format!("Cannot parse range '{}'", input)),
+    };
+
+    match input.split_once("..") {
+        Some((min, max)) => Ok(parse(min, 0.0)?..=parse(max, f32::INFINITY)?),
+        None if input.is_empty() => Err(format!("Cannot parse range '{}'", input)),
+        None => parse(input, 0.0).map(|exact| exact..=exact),
+    }
+}
+
+/// Parses entity in the format it is displayed, for example `12v1`.
+fn parse_entity(input: &str) -> Option<Entity> {
+    let (index, generation) = input.trim().split_once('v')?;
+    let (index, generation) = (index.parse::<u32>().ok()?, generation.parse::<u32>().ok()?);
+    Entity::try_from_bits(((generation as u64) << 32) | index as u64).ok()
+}
+
+/// Resolves [`EntitySelector`] to entities of the world.
+#[derive(SystemParam)]
+pub struct Selectors<'w, 's> {
+    entities: &'w Entities,
+    prototypes: Query<
+        'w,
+        's,
+        (
+            Entity,
+            &'static PrototypeName,
+            Option<&'static Name>,
+            &'static GlobalTransform,
+        ),
+    >,
+    player: Query<'w, 's, (Entity, &'static GlobalTransform), With<Player>>,
+    selection: Option<Res<'w, InspectorSelection>>,
+    /// Random sort uses gameplay randomness, so scripted and recorded sessions repeat.
+    rng: ResMut<'w, GameRng>,
+}
+
+impl Selectors<'_, '_> {
+    /// Matching entities, selector matching nothing is an error.
+    pub fn select(&mut self, selector: &EntitySelector) -> Result<Vec<Entity>, String> {
+        let entities = match selector {
+            EntitySelector::Entity(entity) if self.entities.contains(*entity) => vec![*entity],
+            EntitySelector::Entity(entity) => {
+                return Err(format!("Entity {} does not exist", entity));
+            }
+            EntitySelector::Player => match self.player.get_single() {
+                Ok((player, _)) => vec![player],
+                Err(_) => return Err("Player does not exist".to_string()),
+            },
+            EntitySelector::Selected => self
+                .selection
+                .as_ref()
+                .map(|selection| selection.entities().to_vec())
+                .unwrap_or_default(),
+            EntitySelector::All(filter) => self.filter(filter)?,
+        };
+
+        match entities.is_empty() {
+            true => Err("No entity matches the selector".to_string()),
+            false => Ok(entities),
+        }
+    }
+
+    /// The only matching entity, for commands that cannot handle more.
+    pub fn select_one(&mut self, selector: &EntitySelector) -> Result<Entity, String> {
+        match self.select(selector)?.as_slice() {
+            &[entity] => Ok(entity),
+            entities => Err(format!(
+                "Selector matches {} entities, only one is expected (use limit=1)",
+                entities.len()
+            )),
+        }
+    }
+
+    fn filter(&mut self, filter: &SelectorFilter) -> Result<Vec<Entity>, String> {
+        let needs_player = filter.distance.is_some()
+            || matches!(filter.sort, SelectorSort::Nearest | SelectorSort::Furthest);
+        let origin = match self.player.get_single() {
+            Ok((_, transform)) => transform.translation(),
+            Err(_) if needs_player => {
+                return Err("Distance is measured from the player, which does not exist".into());
+            }
+            Err(_) => Vec3::ZERO,
+        };
+
+        let mut entities = self
+            .prototypes
+            .iter()
+            .filter(|(_, prototype, name, _)| {
+                filter
+                    .kind
+                    .as_ref()
+                    .is_none_or(|kind| kind.matches(Some(&prototype.0)))
+                    && filter
+                        .name
+                        .as_ref()
+                        .is_none_or(|matcher| matcher.matches(name.map(|n| n.as_str())))
+            })
+            .map(|(entity, _, _, transform)| (entity, transform.translation().distance(origin)))
+            .filter(|(_, distance)| {
+                filter
+                    .distance
+                    .as_ref()
+                    .is_none_or(|range| range.contains(distance))
+            })
+            .collect::<Vec<_>>();
+
+        match filter.sort {
+            SelectorSort::Arbitrary => entities.sort_by_key(|(entity, _)| *entity),
+            SelectorSort::Nearest => entities.sort_by(|(_, a), (_, b)| a.total_cmp(b)),
+            SelectorSort::Furthest => entities.sort_by(|(_, a), (_, b)| b.total_cmp(a)),
+            SelectorSort::Random => entities.shuffle(&mut **self.rng),
+        }
+
+        let limit = filter.limit.unwrap_or(usize::MAX);
+        Ok(entities
+            .into_iter()
+            .take(limit)
+            .map(|(entity, _)| entity)
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_simple_selectors() {
+        assert_eq!("@p".parse(), Ok(EntitySelector::Player));
+        assert_eq!("@s".parse(), Ok(EntitySelector::Selected));
+        assert_eq!(
+            "12v1".parse(),
+            Ok(EntitySelector::Entity(Entity::from_bits((1 << 32) | 12)))
+        );
+    }
+
+    #[test]
+    fn parses_filter_arguments() {
+        let selector = "@e[type=Enemy,distance=..10,limit=5,sort=nearest]".parse();
+
+        assert_eq!(
+            selector,
+            Ok(EntitySelector::All(SelectorFilter {
+                kind: Some(Matcher::parse("Enemy")),
+                name: None,
+                distance: Some(0.0..=10.0),
+                limit: Some(5),
+                sort: SelectorSort::Nearest,
+            }))
+        );
+    }
+
+    #[test]
+    fn parses_ranges() {
+        assert_eq!(parse_range("..10"), Ok(0.0..=10.0));
+        assert_eq!(parse_range("5.."), Ok(5.0..=f32::INFINITY));
+        assert_eq!(parse_range("3"), Ok(3.0..=3.0));
+        assert!(parse_range("").is_err());
+    }
+
+    #[test]
+    fn rejects_unknown_argument() {
+        let error = EntitySelector::from_str("@e[colour=red]").unwrap_err();
+
+        assert!(
+            error.starts_with("Unknown selector argument 'colour'"),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn rejects_unbalanced_bracket() {
+        let error = EntitySelector::from_str("@e[type=Enemy").unwrap_err();
+
+        assert!(error.starts_with("Missing ']'"), "{}", error);
+    }
+}
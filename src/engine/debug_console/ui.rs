@@ -0,0 +1,311 @@
+use std::collections::VecDeque;
+use std::fs;
+use std::path::PathBuf;
+
+use bevy::prelude::*;
+use bevy_console::{
+    ConsoleCommand, ConsoleCommandEntered, ConsoleConfiguration, ConsoleOpen, PrintConsoleLine,
+};
+use bevy_inspector_egui::bevy_egui::EguiContexts;
+use bevy_inspector_egui::egui::text::{CCursor, CCursorRange};
+use bevy_inspector_egui::egui::text_edit::TextEditState;
+use bevy_inspector_egui::egui::{self, Key, Modifiers, ScrollArea, TextEdit};
+use clap::Parser;
+use shlex::Shlex;
+
+use super::completion::{Completion, Completions, complete, did_you_mean};
+use crate::engine::prototype::PrototypeName;
+
+const INPUT_HEIGHT: f32 = 30.0;
+
+/// Console window replacing the one from `bevy_console`, which cannot be extended
+/// with completion. Commands are still parsed and run by `bevy_console`.
+#[derive(Resource)]
+pub(super) struct ConsoleState {
+    input: String,
+    scrollback: Vec<String>,
+    /// Entered lines, the newest first, they are saved to `history_file`.
+    history: VecDeque<String>,
+    /// Entry of the history shown in the input, browsed with arrow keys.
+    history_index: Option<usize>,
+    history_file: PathBuf,
+}
+
+impl ConsoleState {
+    pub fn new(history_file: PathBuf) -> Self {
+        // missing file means empty history
+        let history = fs::read_to_string(&history_file)
+            .map(|content| content.lines().rev().map(str::to_string).collect())
+            .unwrap_or_default();
+
+        Self {
+            input: String::new(),
+            scrollback: Vec::new(),
+            history,
+            history_index: None,
+            history_file,
+        }
+    }
+
+    fn add_history(&mut self, line: &str, size: usize) {
+        if self.history.front().is_some_and(|last| last == line) {
+            return;
+        }
+
+        self.history.push_front(line.to_string());
+        self.history.truncate(size);
+        let content = self
+            .history
+            .iter()
+            .rev()
+            .fold(String::new(), |content, line| content + line + "\n");
+        if let Err(error) = fs::write(&self.history_file, content) {
+            warn!(
+                "Cannot write console history '{}': {}",
+                self.history_file.display(),
+                error
+            );
+        }
+    }
+
+    fn browse_history(&mut self, older: bool) {
+        let index = match (self.history_index, older) {
+            (None, true) => Some(0),
+            (Some(index), true) => Some((index + 1).min(self.history.len().saturating_sub(1))),
+            (Some(0) | None, false) => None,
+            (Some(index), false) => Some(index - 1),
+        };
+
+        self.history_index = index.filter(|index| *index < self.history.len());
+        self.input = match self.history_index {
+            Some(index) => self.history[index].clone(),
+            None => String::new(),
+        };
+    }
+
+    fn submit(
+        &mut self,
+        config: &ConsoleConfiguration,
+        entered: &mut EventWriter<ConsoleCommandEntered>,
+    ) {
+        let line = std::mem::take(&mut self.input).trim().to_string();
+        self.history_index = None;
+        if line.is_empty() {
+            return;
+        }
+
+        self.scrollback.push(format!("{}{}", config.symbol, line));
+        self.add_history(&line, config.history_size);
+
+        let mut args = Shlex::new(&line).collect::<Vec<_>>();
+        if args.is_empty() {
+            return;
+        }
+
+        let command_name = args.remove(0);
+        if config.commands.contains_key(command_name.as_str()) {
+            entered.send(ConsoleCommandEntered { command_name, args });
+            return;
+        }
+
+        let suggestion = did_you_mean(&command_name, config.commands.keys().copied())
+            .map(|name| format!(", did you mean '{}'?", name))
+            .unwrap_or_default();
+        self.scrollback
+            .push(format!("Unknown command '{}'{}", command_name, suggestion));
+    }
+}
+
+pub(super) fn console_ui(
+    mut contexts: EguiContexts,
+    config: Res<ConsoleConfiguration>,
+    completions: Res<Completions>,
+    keys: Res<ButtonInput<KeyCode>>,
+    mut state: ResMut<ConsoleState>,
+    mut console: ResMut<ConsoleOpen>,
+    mut entered: EventWriter<ConsoleCommandEntered>,
+    prototypes: Query<(Entity, &PrototypeName)>,
+) {
+    // there is no context when the app is exiting
+    let Some(ctx) = contexts.try_ctx_mut() else {
+        return;
+    };
+
+    // typing the key into another text input does not open the console
+    if keys.any_just_pressed(config.keys.iter().copied())
+        && (console.open || !ctx.wants_keyboard_input())
+    {
+        console.open = !console.open;
+    }
+
+    if !console.open {
+        return;
+    }
+
+    let entities = prototypes
+        .iter()
+        .map(|(entity, prototype)| (entity, prototype.0.clone()))
+        .collect::<Vec<_>>();
+    let completion = complete(&state.input, &config, &completions, &entities);
+
+    egui::Window::new(&config.title_name)
+        .collapsible(config.collapsible)
+        .default_pos([config.left_pos, config.top_pos])
+        .default_size([config.width, config.height])
+        .resizable(config.resizable)
+        .movable(config.moveable)
+        .title_bar(config.show_title_bar)
+        .frame(egui::Frame {
+            fill: config.background_color,
+            ..default()
+        })
+        .show(ctx, |ui| {
+            ui.style_mut().visuals.extreme_bg_color = config.background_color;
+            ui.style_mut().visuals.override_text_color = Some(config.foreground_color);
+
+            let scroll_height = ui.available_height() - INPUT_HEIGHT;
+            ScrollArea::vertical()
+                .auto_shrink([false, false])
+                .stick_to_bottom(true)
+                .max_height(scroll_height)
+                .show(ui, |ui| {
+                    for line in &state.scrollback {
+                        ui.monospace(line);
+                    }
+                });
+            ui.separator();
+
+            // keys are taken before the input, so it does not move focus or cursor with them
+            let (tab, up, down) = ui.input_mut(|input| {
+                (
+                    input.consume_key(Modifiers::NONE, Key::Tab),
+                    input.consume_key(Modifiers::NONE, Key::ArrowUp),
+                    input.consume_key(Modifiers::NONE, Key::ArrowDown),
+                )
+            });
+
+            let response = ui.add(
+                TextEdit::singleline(&mut state.input)
+                    .desired_width(f32::INFINITY)
+                    .lock_focus(true)
+                    .font(egui::TextStyle::Monospace),
+            );
+
+            let mut moved_cursor = true;
+            if response.lost_focus() && ui.input(|input| input.key_pressed(Key::Enter)) {
+                state.submit(&config, &mut entered);
+                response.request_focus();
+            } else if tab {
+                if let Some(line) = completion.apply(&state.input) {
+                    state.input = line;
+                }
+            } else if up || down {
+                state.browse_history(up);
+            } else {
+                moved_cursor = false;
+            }
+
+            if console.is_changed() {
+                response.request_focus();
+            }
+            if moved_cursor {
+                move_cursor_to_end(ui.ctx(), response.id, &state.input);
+            }
+
+            show_suggestions(ui, &completion, config.num_suggestions);
+        });
+}
+
+fn show_suggestions(ui: &mut egui::Ui, completion: &Completion, count: usize) {
+    for candidate in completion.candidates.iter().take(count) {
+        match &candidate.hint {
+            Some(hint) => ui.monospace(format!("{} - {}", candidate.value, hint)),
+            None => ui.monospace(&candidate.value),
+        };
+    }
+    if completion.candidates.len() > count {
+        ui.monospace(format!(
+            "... {} more, press Tab to complete",
+            completion.candidates.len() - count
+        ));
+    }
+}
+
+fn move_cursor_to_end(ctx: &egui::Context, id: egui::Id, text: &str) {
+    if let Some(mut state) = TextEditState::load(ctx, id) {
+        let end = CCursor::new(text.chars().count());
+        state.cursor.set_char_range(Some(CCursorRange::one(end)));
+        state.store(ctx, id);
+    }
+}
+
+pub(super) fn receive_console_lines(
+    mut lines: EventReader<PrintConsoleLine>,
+    mut state: ResMut<ConsoleState>,
+) {
+    for line in lines.read() {
+        state.scrollback.push(line.line.clone());
+    }
+}
+
+pub(super) fn log_console_lines(mut lines: EventReader<PrintConsoleLine>) {
+    for line in lines.read() {
+        info!("{}", line.line);
+    }
+}
+
+#[derive(Parser, ConsoleCommand)]
+#[command(name = "help", about = "Lists commands, or describes one of them")]
+pub(super) struct HelpCommand {
+    command: Option<String>,
+}
+
+#[derive(Parser, ConsoleCommand)]
+#[command(name = "clear", about = "Clears the console")]
+pub(super) struct ClearCommand;
+
+#[derive(Parser, ConsoleCommand)]
+#[command(name = "exit", about = "Closes the game")]
+pub(super) struct ExitCommand;
+
+pub(super) fn help(mut command: ConsoleCommand<HelpCommand>, config: Res<ConsoleConfiguration>) {
+    let Some(Ok(HelpCommand { command: name })) = command.take() else {
+        return;
+    };
+
+    let Some(name) = name else {
+        let width = config.commands.keys().map(|name| name.len()).max();
+        for (name, info) in config.commands.iter() {
+            let about = info.get_about().map(|a| a.to_string()).unwrap_or_default();
+            command.reply(format!(
+                "{:width$} - {}",
+                name,
+                about,
+                width = width.unwrap_or_default()
+            ));
+        }
+        return;
+    };
+
+    match config.commands.get(name.as_str()) {
+        Some(info) => command.reply(info.clone().render_long_help().to_string()),
+        None => {
+            let suggestion = did_you_mean(&name, config.commands.keys().copied())
+                .map(|found| format!(", did you mean '{}'?", found))
+                .unwrap_or_default();
+            command.reply(format!("Unknown command '{}'{}", name, suggestion));
+        }
+    }
+}
+
+pub(super) fn clear(mut command: ConsoleCommand<ClearCommand>, mut state: ResMut<ConsoleState>) {
+    if let Some(Ok(ClearCommand)) = command.take() {
+        state.scrollback.clear();
+    }
+}
+
+pub(super) fn exit(mut command: ConsoleCommand<ExitCommand>, mut exit: EventWriter<AppExit>) {
+    if let Some(Ok(ExitCommand)) = command.take() {
+        exit.send(AppExit::Success);
+    }
+}
@@ -0,0 +1,197 @@
+use std::f32::consts::FRAC_PI_2;
+
+use bevy::prelude::*;
+
+use super::camera::{GameCamera, GameCameraTarget};
+use super::character::Health;
+use super::character::controller::CharacterController;
+use super::character::npc::Npc;
+use super::character::player::Player;
+use super::interaction::{Interactable, InteractionFocus, InteractionKind, MAX_INTERACTION_RANGE};
+use super::item::Item;
+use super::item::storage::ItemStorage;
+use super::navigation::NavPath;
+
+pub struct DebugGizmosPlugin;
+
+impl Plugin for DebugGizmosPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<DebugGizmos>();
+        app.init_resource::<DebugGizmos>();
+        app.add_systems(
+            Update,
+            (
+                draw_pickup_ranges.run_if(|gizmos: Res<DebugGizmos>| gizmos.pickup_ranges),
+                draw_npc_targets.run_if(|gizmos: Res<DebugGizmos>| gizmos.npc_targets),
+                draw_camera_target.run_if(|gizmos: Res<DebugGizmos>| gizmos.camera),
+                draw_storage_links.run_if(|gizmos: Res<DebugGizmos>| gizmos.storage),
+                draw_health_bars.run_if(|gizmos: Res<DebugGizmos>| gizmos.health_bars),
+            ),
+        );
+    }
+}
+
+/// Gameplay state overlays, navigation grid has its own switch in
+/// [`NavigationDebug`](super::navigation::NavigationDebug).
+#[derive(Resource, Default, Reflect, Debug)]
+#[reflect(Resource)]
+pub struct DebugGizmos {
+    /// Ranges of items that can be picked up near the player.
+    pub pickup_ranges: bool,
+    /// Targets and paths of moving NPCs.
+    pub npc_targets: bool,
+    /// Followed entity and look direction of the game camera.
+    pub camera: bool,
+    /// Links between storages and items inside of them.
+    pub storage: bool,
+    pub health_bars: bool,
+}
+
+const HEALTH_BAR_WIDTH: f32 = 1.0;
+/// Stored items are drawn as a stack of boxes above their storage.
+const STORED_ITEM_SIZE: f32 = 0.2;
+
+fn flat_circle(position: Vec3) -> Isometry3d {
+    Isometry3d::new(position, Quat::from_rotation_x(FRAC_PI_2))
+}
+
+fn draw_pickup_ranges(
+    mut gizmos: Gizmos,
+    player: Option<Single<&GlobalTransform, With<Player>>>,
+    interactables: Query<(Entity, &GlobalTransform, &Interactable)>,
+    focus: Res<InteractionFocus>,
+) {
+    let Some(player) = player else {
+        return;
+    };
+    let player = player.translation();
+
+    gizmos.circle(
+        flat_circle(player),
+        MAX_INTERACTION_RANGE,
+        Color::linear_rgba(1.0, 1.0, 1.0, 0.2),
+    );
+
+    for (entity, transform, interactable) in interactables.iter() {
+        let position = transform.translation();
+        if interactable.kind != InteractionKind::PickUp
+            || position.distance(player) > MAX_INTERACTION_RANGE
+        {
+            continue;
+        }
+
+        let color = if focus.0 == Some(entity) {
+            Color::linear_rgb(0.2, 1.0, 0.2)
+        } else if position.distance(player) <= interactable.range {
+            Color::linear_rgb(1.0, 0.8, 0.2)
+        } else {
+            Color::linear_rgb(0.6, 0.6, 0.6)
+        };
+        gizmos.circle(flat_circle(position), interactable.range, color);
+    }
+}
+
+fn draw_npc_targets(mut gizmos: Gizmos, npcs: Query<(&GlobalTransform, &Npc, &NavPath)>) {
+    for (transform, npc, path) in npcs.iter() {
+        let Npc::Moving(target) = npc else {
+            continue;
+        };
+
+        let position = transform.translation();
+        gizmos.sphere(
+            Isometry3d::from_translation(*target),
+            0.2,
+            Color::linear_rgb(1.0, 0.4, 1.0),
+        );
+        if path.waypoints.is_empty() {
+            // path is not found yet, or there is none
+            gizmos.line(position, *target, Color::linear_rgb(0.6, 0.2, 0.6));
+        } else {
+            gizmos.linestrip(
+                std::iter::once(position).chain(path.waypoints.iter().copied()),
+                Color::linear_rgb(1.0, 0.4, 1.0),
+            );
+        }
+    }
+}
+
+fn draw_camera_target(
+    mut gizmos: Gizmos,
+    camera: Option<Single<(&GlobalTransform, &GameCamera)>>,
+    targets: Query<Entity, With<GameCameraTarget>>,
+    transforms: Query<&GlobalTransform>,
+) {
+    let Some(camera) = camera else {
+        return;
+    };
+    let (camera_transform, camera) = camera.into_inner();
+
+    let target = camera
+        .target()
+        .filter(|target| transforms.contains(*target))
+        .or_else(|| targets.iter().next())
+        .and_then(|target| transforms.get(target).ok());
+    let Some(target) = target.map(GlobalTransform::translation) else {
+        return;
+    };
+
+    let color = Color::linear_rgb(0.2, 0.8, 1.0);
+    gizmos.sphere(Isometry3d::from_translation(target), 0.3, color);
+    gizmos.line(target, camera_transform.translation(), color);
+
+    let mut forward = -camera.direction().as_vec3();
+    forward.y = 0.0;
+    gizmos.arrow(target, target + forward.normalize_or_zero() * 2.0, color);
+}
+
+fn draw_storage_links(
+    mut gizmos: Gizmos,
+    storages: Query<(&GlobalTransform, &Children, Option<&CharacterController>), With<ItemStorage>>,
+    items: Query<(), With<Item>>,
+) {
+    for (transform, children, controller) in storages.iter() {
+        let top = transform.translation() + Vec3::Y * controller.map_or(0.5, |c| c.half_height());
+        let stored = children
+            .iter()
+            .filter(|child| items.contains(**child))
+            .count();
+        if stored == 0 {
+            continue;
+        }
+
+        let color = Color::linear_rgb(0.9, 0.7, 0.3);
+        let stack_bottom = top + Vec3::Y * 0.5;
+        gizmos.line(top, stack_bottom, color);
+        for index in 0..stored {
+            let center = stack_bottom + Vec3::Y * STORED_ITEM_SIZE * (index as f32 + 0.5);
+            gizmos.cuboid(
+                Transform::from_translation(center).with_scale(Vec3::splat(STORED_ITEM_SIZE)),
+                color,
+            );
+        }
+    }
+}
+
+fn draw_health_bars(
+    mut gizmos: Gizmos,
+    characters: Query<(&GlobalTransform, &Health, Option<&CharacterController>)>,
+    camera: Option<Single<&GlobalTransform, With<GameCamera>>>,
+) {
+    // bars are drawn along the screen, so they are readable from any angle
+    let right = camera.map_or(Vec3::X, |camera| camera.right().as_vec3());
+
+    for (transform, health, controller) in characters.iter() {
+        let top = controller.map_or(1.0, |c| c.half_height());
+        let center = transform.translation() + Vec3::Y * (top + 0.4);
+        let start = center - right * HEALTH_BAR_WIDTH * 0.5;
+        let fraction = health.current as f32 / health.max.max(1) as f32;
+        let filled = start + right * HEALTH_BAR_WIDTH * fraction.clamp(0.0, 1.0);
+
+        gizmos.line(start, start + right * HEALTH_BAR_WIDTH, Color::BLACK);
+        gizmos.line(
+            start,
+            filled,
+            Color::linear_rgb(1.0 - fraction, fraction, 0.0),
+        );
+    }
+}
@@ -0,0 +1,55 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+
+/// Runs the game without window and renderer for fixed number of frames,
+/// then logs frame time summary and exits. Used for benchmarking heavy scenes.
+pub struct HeadlessPlugin {
+    pub frames: u32,
+}
+
+impl Plugin for HeadlessPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(HeadlessRun {
+            frames: self.frames,
+            ..default()
+        });
+        app.add_systems(Last, measure_headless_run);
+    }
+}
+
+#[derive(Resource, Default, Debug)]
+pub struct HeadlessRun {
+    pub frames: u32,
+    pub elapsed: u32,
+    pub total: Duration,
+    pub worst: Duration,
+}
+
+pub(super) fn measure_headless_run(
+    mut run: ResMut<HeadlessRun>,
+    time: Res<Time<Real>>,
+    entities: Query<()>,
+    mut exit: EventWriter<AppExit>,
+) {
+    // first frame includes startup systems, do not count it
+    if time.delta() != Duration::ZERO && run.elapsed > 0 {
+        run.total += time.delta();
+        run.worst = run.worst.max(time.delta());
+    }
+    run.elapsed += 1;
+
+    if run.elapsed <= run.frames {
+        return;
+    }
+
+    let measured = run.frames.max(1);
+    info!(
+        "Headless run finished: {} frames, {} entities, average {:.3} ms, worst {:.3} ms",
+        run.frames,
+        entities.iter().count(),
+        run.total.as_secs_f64() * 1000.0 / measured as f64,
+        run.worst.as_secs_f64() * 1000.0
+    );
+    exit.send(AppExit::Success);
+}
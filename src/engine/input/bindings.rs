@@ -0,0 +1,529 @@
+use std::collections::{BTreeMap, HashMap};
+
+use bevy::input::gamepad::GamepadInput;
+use bevy::input::mouse::{AccumulatedMouseMotion, AccumulatedMouseScroll};
+use bevy::prelude::*;
+use derive_more::derive::{Display, FromStr};
+use serde::{Deserialize, Serialize};
+
+use crate::engine::config::ConfigSection;
+
+#[derive(
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Display,
+    FromStr,
+    Serialize,
+    Deserialize,
+    Reflect,
+    Debug,
+)]
+pub enum InputAction {
+    MoveForward,
+    MoveBackward,
+    MoveLeft,
+    MoveRight,
+    Sprint,
+    ZoomIn,
+    ZoomOut,
+    ToggleInventory,
+    Interact,
+    RotateCameraLeft,
+    RotateCameraRight,
+    PitchCameraUp,
+    PitchCameraDown,
+    /// Held to rotate the camera with mouse movement.
+    OrbitCamera,
+    PauseTime,
+    /// Runs a single simulation step, pausing the time first.
+    StepTime,
+    SlowDownTime,
+    SpeedUpTime,
+}
+
+#[derive(Clone, Copy, Default, PartialEq, Eq, Display, Serialize, Deserialize, Reflect, Debug)]
+pub enum InputDevice {
+    #[default]
+    KeyboardMouse,
+    Gamepad,
+}
+
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize, Reflect, Debug)]
+pub enum InputBinding {
+    Key(KeyCode),
+    Mouse(MouseButton),
+    MouseWheelUp,
+    MouseWheelDown,
+    GamepadButton(GamepadButton),
+    /// Positive half of gamepad axis, for example right for `LeftStickX`.
+    GamepadAxisPositive(GamepadAxis),
+    /// Negative half of gamepad axis, for example left for `LeftStickX`.
+    GamepadAxisNegative(GamepadAxis),
+}
+
+impl InputBinding {
+    /// Parses binding in the format used by console commands, for example `Key(KeyW)`.
+    pub fn parse(input: &str) -> Result<Self, String> {
+        ron::from_str(input.trim()).map_err(|e| format!("Cannot parse binding '{}': {}", input, e))
+    }
+
+    pub fn device(&self) -> InputDevice {
+        match self {
+            Self::Key(_) | Self::Mouse(_) | Self::MouseWheelUp | Self::MouseWheelDown => {
+                InputDevice::KeyboardMouse
+            }
+            Self::GamepadButton(_)
+            | Self::GamepadAxisPositive(_)
+            | Self::GamepadAxisNegative(_) => InputDevice::Gamepad,
+        }
+    }
+
+    /// Impulse bindings report distance travelled during the frame instead of held state,
+    /// so their value should not be scaled by frame time.
+    pub fn is_impulse(&self) -> bool {
+        matches!(self, Self::MouseWheelUp | Self::MouseWheelDown)
+    }
+
+    pub fn value(&self, devices: &InputDevices) -> f32 {
+        let gamepad = |input: GamepadInput| {
+            devices
+                .gamepad
+                .and_then(|g| g.get(input))
+                .unwrap_or_default()
+        };
+
+        match self {
+            Self::Key(key) => devices.keyboard.pressed(*key) as u8 as f32,
+            Self::Mouse(button) => devices.mouse.pressed(*button) as u8 as f32,
+            Self::MouseWheelUp => devices.scroll.delta.y.max(0.0),
+            Self::MouseWheelDown => (-devices.scroll.delta.y).max(0.0),
+            Self::GamepadButton(button) => {
+                let pressed = devices.gamepad.is_some_and(|g| g.pressed(*button)) as u8 as f32;
+                gamepad((*button).into()).max(pressed)
+            }
+            Self::GamepadAxisPositive(axis) => gamepad((*axis).into()).max(0.0),
+            Self::GamepadAxisNegative(axis) => (-gamepad((*axis).into())).max(0.0),
+        }
+    }
+
+    /// Gamepad bindings are pressed above [`AXIS_PRESS_THRESHOLD`], so stick drift or a lightly
+    /// touched trigger does not hold button actions.
+    pub fn pressed(&self, devices: &InputDevices) -> bool {
+        match self.device() {
+            InputDevice::KeyboardMouse => self.value(devices) > 0.0,
+            InputDevice::Gamepad => self.value(devices) >= AXIS_PRESS_THRESHOLD,
+        }
+    }
+
+    pub fn just_pressed(&self, devices: &InputDevices) -> bool {
+        match self {
+            Self::Key(key) => devices.keyboard.just_pressed(*key),
+            Self::Mouse(button) => devices.mouse.just_pressed(*button),
+            Self::GamepadButton(button) => devices.gamepad.is_some_and(|g| g.just_pressed(*button)),
+            Self::MouseWheelUp | Self::MouseWheelDown => self.value(devices) > 0.0,
+            // stick held past the threshold counts once, like a held button
+            Self::GamepadAxisPositive(axis) => {
+                self.value(devices) >= AXIS_PRESS_THRESHOLD
+                    && devices.previous_axes.get(*axis) < AXIS_PRESS_THRESHOLD
+            }
+            Self::GamepadAxisNegative(axis) => {
+                self.value(devices) >= AXIS_PRESS_THRESHOLD
+                    && -devices.previous_axes.get(*axis) < AXIS_PRESS_THRESHOLD
+            }
+        }
+    }
+}
+
+/// Gamepad axis or analog button value above which it is pressed like a button.
+const AXIS_PRESS_THRESHOLD: f32 = 0.5;
+
+impl std::fmt::Display for InputBinding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&ron::to_string(self).map_err(|_| std::fmt::Error)?)
+    }
+}
+
+/// Raw device state that bindings are evaluated against.
+pub struct InputDevices<'a> {
+    pub keyboard: &'a ButtonInput<KeyCode>,
+    pub mouse: &'a ButtonInput<MouseButton>,
+    pub mouse_motion: &'a AccumulatedMouseMotion,
+    pub scroll: &'a AccumulatedMouseScroll,
+    pub gamepad: Option<&'a Gamepad>,
+    pub previous_axes: &'a GamepadAxisHistory,
+}
+
+impl InputDevices<'_> {
+    /// Device that received input in this frame, gamepad wins if both were used.
+    pub fn used_device(&self) -> Option<InputDevice> {
+        const STICK_THRESHOLD: f32 = 0.2;
+        // pixels per frame, so bumping the desk does not take over from a gamepad
+        const MOUSE_MOTION_THRESHOLD: f32 = 8.0;
+
+        let gamepad_used = self.gamepad.is_some_and(|g| {
+            g.get_just_pressed().next().is_some()
+                || g.left_stick().length() > STICK_THRESHOLD
+                || g.right_stick().length() > STICK_THRESHOLD
+        });
+        let keyboard_mouse_used = self.keyboard.get_just_pressed().next().is_some()
+            || self.mouse.get_just_pressed().next().is_some()
+            || self.mouse_motion.delta.length() > MOUSE_MOTION_THRESHOLD
+            || self.scroll.delta != Vec2::ZERO;
+
+        match (gamepad_used, keyboard_mouse_used) {
+            (true, _) => Some(InputDevice::Gamepad),
+            (false, true) => Some(InputDevice::KeyboardMouse),
+            (false, false) => None,
+        }
+    }
+}
+
+/// Gamepad axis values of the previous frame, to detect when a half axis is just pressed.
+#[derive(Resource, Default, Debug)]
+pub struct GamepadAxisHistory(HashMap<GamepadAxis, f32>);
+
+impl GamepadAxisHistory {
+    pub fn get(&self, axis: GamepadAxis) -> f32 {
+        self.0.get(&axis).copied().unwrap_or_default()
+    }
+}
+
+/// Bindings of every [`InputAction`], persisted in `bindings` settings section.
+/// Actions missing in it (for example added later) get default bindings.
+#[derive(Resource, Clone, PartialEq, Serialize, Deserialize, Reflect, Debug)]
+#[reflect(Resource)]
+pub struct InputMap(BTreeMap<InputAction, Vec<InputBinding>>);
+
+impl Default for InputMap {
+    fn default() -> Self {
+        use InputAction::*;
+        use InputBinding::*;
+        use bevy::input::gamepad::GamepadButton as Button;
+
+        Self(BTreeMap::from([
+            (MoveForward, vec![
+                Key(KeyCode::KeyW),
+                GamepadAxisPositive(GamepadAxis::LeftStickY),
+            ]),
+            (MoveBackward, vec![
+                Key(KeyCode::KeyS),
+                GamepadAxisNegative(GamepadAxis::LeftStickY),
+            ]),
+            (MoveLeft, vec![
+                Key(KeyCode::KeyA),
+                GamepadAxisNegative(GamepadAxis::LeftStickX),
+            ]),
+            (MoveRight, vec![
+                Key(KeyCode::KeyD),
+                GamepadAxisPositive(GamepadAxis::LeftStickX),
+            ]),
+            (Sprint, vec![
+                Key(KeyCode::ShiftLeft),
+                GamepadButton(Button::RightTrigger2),
+            ]),
+            (ZoomIn, vec![
+                MouseWheelUp,
+                GamepadAxisPositive(GamepadAxis::RightStickY),
+            ]),
+            (ZoomOut, vec![
+                MouseWheelDown,
+                GamepadAxisNegative(GamepadAxis::RightStickY),
+            ]),
+            (ToggleInventory, vec![
+                Key(KeyCode::Tab),
+                GamepadButton(Button::Select),
+            ]),
+            (Interact, vec![
+                Key(KeyCode::KeyE),
+                GamepadButton(Button::South),
+            ]),
+            // E is taken by interaction, so camera keys are next to it instead
+            (RotateCameraLeft, vec![
+                Key(KeyCode::KeyQ),
+                GamepadAxisNegative(GamepadAxis::RightStickX),
+            ]),
+            (RotateCameraRight, vec![
+                Key(KeyCode::KeyR),
+                GamepadAxisPositive(GamepadAxis::RightStickX),
+            ]),
+            (PitchCameraUp, vec![
+                Key(KeyCode::PageUp),
+                GamepadButton(Button::DPadUp),
+            ]),
+            (PitchCameraDown, vec![
+                Key(KeyCode::PageDown),
+                GamepadButton(Button::DPadDown),
+            ]),
+            (OrbitCamera, vec![Mouse(MouseButton::Right)]),
+            (PauseTime, vec![Key(KeyCode::F5)]),
+            (StepTime, vec![Key(KeyCode::F6)]),
+            (SlowDownTime, vec![Key(KeyCode::F7)]),
+            (SpeedUpTime, vec![Key(KeyCode::F8)]),
+        ]))
+    }
+}
+
+impl ConfigSection for InputMap {
+    const NAME: &'static str = "bindings";
+}
+
+impl InputMap {
+    pub fn bindings(&self, action: InputAction) -> &[InputBinding] {
+        self.0.get(&action).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (InputAction, &[InputBinding])> {
+        self.0.iter().map(|(a, b)| (*a, b.as_slice()))
+    }
+
+    /// Action other than `action` that already uses the binding.
+    pub fn conflict(&self, action: InputAction, binding: InputBinding) -> Option<InputAction> {
+        self.iter()
+            .find(|(a, bindings)| *a != action && bindings.contains(&binding))
+            .map(|(a, _)| a)
+    }
+
+    /// All bindings used by more than one action.
+    pub fn conflicts(&self) -> Vec<(InputBinding, Vec<InputAction>)> {
+        let mut conflicts = Vec::<(InputBinding, Vec<InputAction>)>::new();
+        for (action, bindings) in self.iter() {
+            for binding in bindings {
+                match conflicts.iter_mut().find(|(b, _)| b == binding) {
+                    Some((_, actions)) => actions.push(action),
+                    None => conflicts.push((*binding, vec![action])),
+                }
+            }
+        }
+
+        conflicts.retain(|(_, actions)| actions.len() > 1);
+        conflicts
+    }
+
+    /// Adds binding to the action, fails with conflicting action if binding is already used.
+    pub fn bind(&mut self, action: InputAction, binding: InputBinding) -> Result<(), InputAction> {
+        if let Some(conflict) = self.conflict(action, binding) {
+            return Err(conflict);
+        }
+
+        let bindings = self.0.entry(action).or_default();
+        if !bindings.contains(&binding) {
+            bindings.push(binding);
+        }
+        Ok(())
+    }
+
+    /// Replaces all bindings of the action with a single one.
+    pub fn rebind(
+        &mut self,
+        action: InputAction,
+        binding: InputBinding,
+    ) -> Result<(), InputAction> {
+        if let Some(conflict) = self.conflict(action, binding) {
+            return Err(conflict);
+        }
+
+        self.0.insert(action, vec![binding]);
+        Ok(())
+    }
+
+    pub fn unbind(&mut self, action: InputAction, binding: InputBinding) {
+        if let Some(bindings) = self.0.get_mut(&action) {
+            bindings.retain(|b| *b != binding);
+        }
+    }
+
+    /// Strongest value of action bindings that belong to `device`.
+    pub fn value(&self, action: InputAction, device: InputDevice, devices: &InputDevices) -> f32 {
+        self.bindings(action)
+            .iter()
+            .filter(|b| b.device() == device)
+            .map(|b| b.value(devices))
+            .fold(0.0, f32::max)
+    }
+
+    /// Like [`InputMap::value`], but values of held bindings are treated as rate per second
+    /// and scaled to the frame, while impulse bindings are used as they are.
+    pub fn frame_value(
+        &self,
+        action: InputAction,
+        device: InputDevice,
+        devices: &InputDevices,
+        rate: f32,
+        delta: f32,
+    ) -> f32 {
+        self.bindings(action)
+            .iter()
+            .filter(|b| b.device() == device)
+            .map(|b| {
+                if b.is_impulse() {
+                    b.value(devices)
+                } else {
+                    b.value(devices) * rate * delta
+                }
+            })
+            .fold(0.0, f32::max)
+    }
+
+    pub fn pressed(
+        &self,
+        action: InputAction,
+        device: InputDevice,
+        devices: &InputDevices,
+    ) -> bool {
+        self.bindings(action)
+            .iter()
+            .filter(|b| b.device() == device)
+            .any(|b| b.pressed(devices))
+    }
+
+    pub fn just_pressed(
+        &self,
+        action: InputAction,
+        device: InputDevice,
+        devices: &InputDevices,
+    ) -> bool {
+        self.bindings(action)
+            .iter()
+            .filter(|b| b.device() == device)
+            .any(|b| b.just_pressed(devices))
+    }
+}
+
+/// Action waiting for the next pressed key or button to become its only binding.
+#[derive(Resource, Default, Debug)]
+pub struct PendingRebind(pub Option<InputAction>);
+
+pub(super) fn warn_binding_conflicts(map: Res<InputMap>) {
+    for (binding, actions) in map.conflicts() {
+        warn!(
+            "Binding {} is used by multiple actions: {:?}",
+            binding, actions
+        );
+    }
+}
+
+pub(super) fn record_gamepad_axes(
+    mut history: ResMut<GamepadAxisHistory>,
+    gamepads: Query<&Gamepad>,
+) {
+    let gamepad = gamepads.iter().next();
+    history.0 = GamepadAxis::all()
+        .into_iter()
+        .map(|axis| (axis, gamepad.and_then(|g| g.get(axis)).unwrap_or_default()))
+        .collect();
+}
+
+pub(super) fn capture_rebind(
+    mut pending: ResMut<PendingRebind>,
+    mut map: ResMut<InputMap>,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    gamepads: Query<&Gamepad>,
+) {
+    // skip the frame in which rebinding was requested, so the confirming key is not captured
+    let Some(action) = pending.0.filter(|_| !pending.is_changed()) else {
+        return;
+    };
+
+    if keyboard.just_pressed(KeyCode::Escape) {
+        info!("Rebinding of {} was cancelled", action);
+        pending.0 = None;
+        return;
+    }
+
+    let gamepad_button = gamepads.iter().find_map(|g| g.get_just_pressed().next());
+    let binding = match (
+        keyboard.get_just_pressed().next(),
+        mouse.get_just_pressed().next(),
+        gamepad_button,
+    ) {
+        (Some(key), _, _) => InputBinding::Key(*key),
+        (None, Some(button), _) => InputBinding::Mouse(*button),
+        (None, None, Some(button)) => InputBinding::GamepadButton(*button),
+        (None, None, None) => return,
+    };
+
+    pending.0 = None;
+    match map.rebind(action, binding) {
+        Ok(()) => info!("{} is now bound to {}", action, binding),
+        Err(conflict) => warn!(
+            "Cannot bind {} to {}, it is already used by {}",
+            action, binding, conflict
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::input::gamepad::GamepadButton as Button;
+
+    use super::*;
+
+    #[test]
+    fn analog_gamepad_input_is_pressed_above_threshold() {
+        let map = InputMap::default();
+        let (keyboard, mouse) = (ButtonInput::default(), ButtonInput::default());
+        let (mouse_motion, scroll) = (default(), default());
+        let previous_axes = GamepadAxisHistory::default();
+
+        let cases = [
+            // (analog input, value, action, expected)
+            (
+                GamepadInput::Button(Button::RightTrigger2),
+                0.1,
+                InputAction::Sprint,
+                false,
+            ),
+            (
+                GamepadInput::Button(Button::RightTrigger2),
+                0.8,
+                InputAction::Sprint,
+                true,
+            ),
+            (
+                GamepadInput::Axis(GamepadAxis::RightStickX),
+                0.2,
+                InputAction::RotateCameraRight,
+                false,
+            ),
+            (
+                GamepadInput::Axis(GamepadAxis::RightStickX),
+                0.6,
+                InputAction::RotateCameraRight,
+                true,
+            ),
+            (
+                GamepadInput::Axis(GamepadAxis::RightStickX),
+                -0.6,
+                InputAction::RotateCameraRight,
+                false,
+            ),
+        ];
+
+        for (input, value, action, expected) in cases {
+            let mut gamepad = Gamepad::default();
+            gamepad.analog_mut().set(input, value);
+            let devices = InputDevices {
+                keyboard: &keyboard,
+                mouse: &mouse,
+                mouse_motion: &mouse_motion,
+                scroll: &scroll,
+                gamepad: Some(&gamepad),
+                previous_axes: &previous_axes,
+            };
+
+            assert_eq!(
+                map.pressed(action, InputDevice::Gamepad, &devices),
+                expected,
+                "{:?} at {}",
+                input,
+                value
+            );
+        }
+    }
+}
@@ -0,0 +1,69 @@
+use bevy::prelude::*;
+use derive_more::derive::{Display, FromStr};
+
+use super::bindings::InputAction;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Display, FromStr, Reflect, Debug)]
+pub enum InputContext {
+    Gameplay,
+    Menu,
+    Inventory,
+    Console,
+    Dialog,
+}
+
+impl InputContext {
+    /// Whether the action is live while this context is on top of the stack,
+    /// other actions are consumed and read as released.
+    pub fn allows(&self, action: InputAction) -> bool {
+        match self {
+            Self::Gameplay => true,
+            // inventory can be closed with the same action that opened it
+            Self::Inventory => action == InputAction::ToggleInventory,
+            Self::Menu | Self::Console | Self::Dialog => false,
+        }
+    }
+}
+
+/// Stack of input contexts, only the top one decides which actions reach gameplay.
+/// [`InputContext::Gameplay`] is always at the bottom and cannot be removed.
+#[derive(Resource, Reflect, Debug)]
+#[reflect(Resource)]
+pub struct InputContexts(Vec<InputContext>);
+
+impl Default for InputContexts {
+    fn default() -> Self {
+        Self(vec![InputContext::Gameplay])
+    }
+}
+
+impl InputContexts {
+    pub fn active(&self) -> InputContext {
+        self.0.last().copied().unwrap_or(InputContext::Gameplay)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = InputContext> + '_ {
+        self.0.iter().copied()
+    }
+
+    /// Puts the context on top, context already on the stack is moved instead of duplicated.
+    pub fn push(&mut self, context: InputContext) {
+        if self.active() == context {
+            return;
+        }
+
+        self.remove(context);
+        self.0.push(context);
+    }
+
+    /// Removes the context wherever it is, so closing UI does not depend on the order of closing.
+    pub fn remove(&mut self, context: InputContext) {
+        if context != InputContext::Gameplay {
+            self.0.retain(|c| *c != context);
+        }
+    }
+
+    pub fn allows(&self, action: InputAction) -> bool {
+        self.active().allows(action)
+    }
+}
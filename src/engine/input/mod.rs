@@ -0,0 +1,213 @@
+pub mod bindings;
+pub mod context;
+
+use bevy::input::InputSystem;
+use bevy::input::mouse::{AccumulatedMouseMotion, AccumulatedMouseScroll};
+use bevy::prelude::*;
+use bindings::{
+    GamepadAxisHistory, InputAction, InputDevice, InputDevices, InputMap, PendingRebind,
+    capture_rebind, record_gamepad_axes, warn_binding_conflicts,
+};
+use context::{InputContext, InputContexts};
+use serde::{Deserialize, Serialize};
+
+use super::character::player::PointerTarget;
+use super::config::ConfigAppExt;
+
+pub struct GameInputPlugin;
+
+impl Plugin for GameInputPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<GameplayInput>();
+        app.register_type::<InputMap>();
+        app.register_type::<InputContext>();
+        app.register_type::<InputContexts>();
+        app.init_resource::<GameplayInput>();
+        app.init_resource::<InputContexts>();
+        app.init_resource::<PendingRebind>();
+        app.init_resource::<GamepadAxisHistory>();
+        app.add_config_section::<InputMap>();
+        app.add_systems(Startup, warn_binding_conflicts);
+        app.add_systems(
+            PreUpdate,
+            (capture_rebind, update_gameplay_input)
+                .chain()
+                .in_set(GameplayInputSet)
+                .after(InputSystem),
+        );
+        app.add_systems(FixedLast, consume_edge_actions);
+        // after every reader of this frame's input
+        app.add_systems(Last, record_gamepad_axes);
+    }
+}
+
+/// Systems that fill [`GameplayInput`] in [`PreUpdate`], so fixed simulation steps
+/// see input of the current frame. Systems changing [`InputContexts`] should run before it
+/// to take effect in the same frame.
+#[derive(SystemSet, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct GameplayInputSet;
+
+#[derive(Resource, Clone, Default, Serialize, Deserialize, Reflect, Debug)]
+#[reflect(Resource)]
+pub struct GameplayInput {
+    pub movement: Vec2,
+    pub sprint: bool,
+    pub zoom: f32,
+    /// Pressed since the last fixed step, kept until a step sees it, so the press is not
+    /// lost in frames without a step or repeated in frames with several of them.
+    pub toggle_inventory: bool,
+    /// Pressed since the last fixed step, like [`Self::toggle_inventory`].
+    pub interact: bool,
+    /// Clicked pointer target since the last fixed step, like [`Self::toggle_inventory`].
+    pub order: Option<PointerTarget>,
+    /// Camera yaw (x) and pitch (y) change in radians for this frame.
+    pub camera_rotation: Vec2,
+    /// Camera yaw that movement is relative to, set by the camera after it is rotated.
+    pub view_yaw: f32,
+    /// Last used device, gameplay reads only its bindings and UI shows its button prompts.
+    pub device: InputDevice,
+}
+
+/// Zoom speed of held bindings like gamepad stick, in camera distance units per second.
+const ZOOM_RATE: f32 = 20.0;
+
+/// Camera rotation speed of held bindings like keys or gamepad stick, in radians per second.
+const CAMERA_ROTATION_RATE: f32 = 2.0;
+
+/// Camera rotation in radians per pixel of mouse movement while orbiting.
+const MOUSE_ORBIT_SENSITIVITY: f32 = 0.005;
+
+fn update_gameplay_input(
+    mut input: ResMut<GameplayInput>,
+    map: Res<InputMap>,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    mouse_motion: Res<AccumulatedMouseMotion>,
+    mouse_scroll: Res<AccumulatedMouseScroll>,
+    gamepads: Query<&Gamepad>,
+    axis_history: Res<GamepadAxisHistory>,
+    contexts: Res<InputContexts>,
+    time: Res<Time>,
+) {
+    let devices = InputDevices {
+        keyboard: &keyboard,
+        mouse: &mouse,
+        mouse_motion: &mouse_motion,
+        scroll: &mouse_scroll,
+        gamepad: gamepads.iter().next(),
+        previous_axes: &axis_history,
+    };
+
+    if let Some(device) = devices.used_device() {
+        if input.device != device {
+            input.device = device;
+        }
+    }
+
+    // actions consumed by the active context read as released, so for example typing
+    // in the console does not move the player
+    let device = input.device;
+    let live = |action| contexts.allows(action);
+    let value = |action| {
+        if live(action) {
+            map.value(action, device, &devices)
+        } else {
+            0.0
+        }
+    };
+    let zoom = |action| {
+        if live(action) {
+            map.frame_value(action, device, &devices, ZOOM_RATE, time.delta_secs())
+        } else {
+            0.0
+        }
+    };
+
+    // clamp instead of normalize, so partially tilted stick walks slower
+    input.movement = Vec2::new(
+        value(InputAction::MoveRight) - value(InputAction::MoveLeft),
+        value(InputAction::MoveForward) - value(InputAction::MoveBackward),
+    )
+    .clamp_length_max(1.0);
+
+    input.sprint = live(InputAction::Sprint) && map.pressed(InputAction::Sprint, device, &devices);
+    input.zoom = zoom(InputAction::ZoomIn) - zoom(InputAction::ZoomOut);
+    // cleared by the next fixed step, see consume_edge_actions
+    input.toggle_inventory |= live(InputAction::ToggleInventory)
+        && map.just_pressed(InputAction::ToggleInventory, device, &devices);
+    input.interact |=
+        live(InputAction::Interact) && map.just_pressed(InputAction::Interact, device, &devices);
+
+    let rotate = |action| {
+        if live(action) {
+            map.frame_value(
+                action,
+                device,
+                &devices,
+                CAMERA_ROTATION_RATE,
+                time.delta_secs(),
+            )
+        } else {
+            0.0
+        }
+    };
+    input.camera_rotation = Vec2::new(
+        rotate(InputAction::RotateCameraRight) - rotate(InputAction::RotateCameraLeft),
+        rotate(InputAction::PitchCameraUp) - rotate(InputAction::PitchCameraDown),
+    );
+    if live(InputAction::OrbitCamera) && map.pressed(InputAction::OrbitCamera, device, &devices) {
+        // dragging right moves the camera left around the target, like grabbing the world
+        input.camera_rotation +=
+            Vec2::new(-mouse_motion.delta.x, mouse_motion.delta.y) * MOUSE_ORBIT_SENSITIVITY;
+    }
+}
+
+/// Clears pressed actions once a fixed step has seen them, after it is recorded or replayed.
+fn consume_edge_actions(mut input: ResMut<GameplayInput>) {
+    input.toggle_inventory = false;
+    input.interact = false;
+    input.order = None;
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::input::InputPlugin;
+
+    use super::*;
+    use crate::engine::config::Config;
+
+    fn create_app() -> App {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, InputPlugin));
+        // defaults only, nothing is saved unless bindings change
+        app.insert_resource(Config::new(&std::env::temp_dir().join("settings.json")));
+        app.add_plugins(GameInputPlugin);
+        app
+    }
+
+    fn movement(app: &App) -> Vec2 {
+        app.world().resource::<GameplayInput>().movement
+    }
+
+    #[test]
+    fn context_consumes_movement() {
+        let mut app = create_app();
+        app.world_mut()
+            .resource_mut::<ButtonInput<KeyCode>>()
+            .press(KeyCode::KeyW);
+        app.update();
+        assert_eq!(movement(&app), Vec2::Y);
+
+        app.world_mut()
+            .resource_mut::<InputContexts>()
+            .push(InputContext::Console);
+        app.update();
+        assert_eq!(movement(&app), Vec2::ZERO);
+
+        app.world_mut()
+            .resource_mut::<InputContexts>()
+            .remove(InputContext::Console);
+        app.update();
+        assert_eq!(movement(&app), Vec2::Y);
+    }
+}
@@ -0,0 +1,117 @@
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+use bevy_inspector_egui::bevy_egui::{EguiContext, EguiPlugin};
+use bevy_inspector_egui::bevy_inspector::hierarchy::{SelectedEntities, hierarchy_ui};
+use bevy_inspector_egui::{DefaultInspectorConfigPlugin, bevy_inspector, egui};
+
+use super::time_control::{MAX_TIME_SCALE, MIN_TIME_SCALE, TimeControl};
+
+/// World inspector with entity selection, selected entities are available
+/// to other tools (like console `@s` selector) through [`InspectorSelection`].
+pub struct InspectorPlugin;
+
+impl Plugin for InspectorPlugin {
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<DefaultInspectorConfigPlugin>() {
+            app.add_plugins(DefaultInspectorConfigPlugin);
+        }
+        if !app.is_plugin_added::<EguiPlugin>() {
+            app.add_plugins(EguiPlugin);
+        }
+
+        app.init_resource::<InspectorSelection>();
+        app.add_systems(Update, inspector_ui);
+    }
+}
+
+#[derive(Resource, Default, Debug)]
+pub struct InspectorSelection(SelectedEntities);
+
+impl InspectorSelection {
+    pub fn entities(&self) -> &[Entity] {
+        self.0.as_slice()
+    }
+}
+
+fn inspector_ui(world: &mut World) {
+    let egui_context = world
+        .query_filtered::<&mut EguiContext, With<PrimaryWindow>>()
+        .get_single(world);
+
+    let Ok(egui_context) = egui_context else {
+        return;
+    };
+    let mut egui_context = egui_context.clone();
+
+    world.resource_scope(|world, mut selection: Mut<InspectorSelection>| {
+        // despawned entities would stay selected forever
+        selection
+            .0
+            .retain(|entity| world.get_entity(entity).is_ok());
+
+        egui::Window::new("World Inspector")
+            .default_size((320.0, 160.0))
+            .show(egui_context.get_mut(), |ui| {
+                egui::ScrollArea::both().show(ui, |ui| {
+                    egui::CollapsingHeader::new("Time")
+                        .default_open(true)
+                        .show(ui, |ui| time_ui(world, ui));
+                    egui::CollapsingHeader::new("Entities")
+                        .default_open(true)
+                        .show(ui, |ui| {
+                            hierarchy_ui(world, ui, &mut selection.0);
+                        });
+                    egui::CollapsingHeader::new("Resources").show(ui, |ui| {
+                        bevy_inspector::ui_for_resources(world, ui);
+                    });
+                    egui::CollapsingHeader::new("Assets").show(ui, |ui| {
+                        bevy_inspector::ui_for_all_assets(world, ui);
+                    });
+                    ui.allocate_space(ui.available_size());
+                });
+            });
+
+        if selection.0.is_empty() {
+            return;
+        }
+
+        egui::Window::new("Selected")
+            .default_size((320.0, 160.0))
+            .show(egui_context.get_mut(), |ui| {
+                egui::ScrollArea::both().show(ui, |ui| {
+                    match selection.entities() {
+                        &[entity] => bevy_inspector::ui_for_entity(world, entity, ui),
+                        entities => {
+                            bevy_inspector::ui_for_entities_shared_components(world, entities, ui)
+                        }
+                    }
+                    ui.allocate_space(ui.available_size());
+                });
+            });
+    });
+}
+
+/// Same controls as time console commands and key bindings.
+fn time_ui(world: &mut World, ui: &mut egui::Ui) {
+    world.resource_scope(|world, mut control: Mut<TimeControl>| {
+        let mut time = world.resource_mut::<Time<Virtual>>();
+
+        ui.horizontal(|ui| {
+            let label = if time.is_paused() { "Resume" } else { "Pause" };
+            if ui.button(label).clicked() {
+                control.toggle_pause(&mut time);
+            }
+            if ui.button("Step").clicked() {
+                control.step(&mut time, 1);
+            }
+        });
+
+        let mut scale = time.relative_speed();
+        let slider = egui::Slider::new(&mut scale, MIN_TIME_SCALE..=MAX_TIME_SCALE)
+            .logarithmic(true)
+            .text("Scale");
+        if ui.add(slider).changed() {
+            control.set_scale(&mut time, scale);
+        }
+    });
+}
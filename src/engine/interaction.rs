@@ -0,0 +1,217 @@
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use derive_more::derive::{Display, FromStr};
+use smart_default::SmartDefault;
+
+use super::character::CharacterSet;
+use super::character::player::Player;
+use super::input::GameplayInput;
+use super::input::bindings::{InputAction, InputMap};
+use super::item::Item;
+use super::item::storage::InsertItemCommand;
+use super::spatial::{SpatialIndexed, SpatialQuery};
+
+pub struct InteractionPlugin;
+
+impl Plugin for InteractionPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<Interactable>();
+        app.register_type::<InteractionKind>();
+        app.register_type::<InteractionFocus>();
+        app.init_resource::<InteractionFocus>();
+        app.add_event::<InteractionEvent>();
+        app.add_systems(Startup, spawn_interaction_prompt);
+        // simulation state, so interactions happen at the same step in replays
+        app.add_systems(
+            FixedUpdate,
+            (update_interaction_focus, interact_with_focus, pick_up_items)
+                .chain()
+                .after(CharacterSet::Physics),
+        );
+        app.add_systems(Update, update_interaction_prompt);
+    }
+}
+
+/// Interactions further than this are never considered, keeps spatial queries cheap.
+pub const MAX_INTERACTION_RANGE: f32 = 10.0;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Display, FromStr, Reflect, Debug)]
+pub enum InteractionKind {
+    PickUp,
+    OpenContainer,
+    Talk,
+    UseDoor,
+    Harvest,
+}
+
+/// Something characters can interact with while they are within [`Interactable::range`].
+/// Engine handles [`InteractionKind::PickUp`] of items, other kinds are left to game code
+/// observing [`InteractionEvent`].
+#[derive(Component, SmartDefault, Clone, Reflect, Debug)]
+#[require(Transform, SpatialIndexed)]
+pub struct Interactable {
+    #[default(InteractionKind::PickUp)]
+    pub kind: InteractionKind,
+    #[default("Pick up".to_string())]
+    pub prompt: String,
+    #[default(2.0)]
+    pub range: f32,
+}
+
+impl Interactable {
+    pub fn new(kind: InteractionKind, prompt: impl Into<String>) -> Self {
+        Self {
+            kind,
+            prompt: prompt.into(),
+            ..default()
+        }
+    }
+}
+
+#[derive(Event, Clone, Copy, Debug)]
+pub struct InteractionEvent {
+    pub actor: Entity,
+    pub target: Entity,
+    pub kind: InteractionKind,
+}
+
+/// Nearest interactable within range of the player, used by the interact action.
+#[derive(Resource, Default, Reflect, Debug)]
+#[reflect(Resource)]
+pub struct InteractionFocus(pub Option<Entity>);
+
+/// Resolves which interactable is meant by a position, like character feet or cursor hit.
+#[derive(SystemParam)]
+pub struct Interactables<'w, 's> {
+    spatial: SpatialQuery<'w, 's, (With<Interactable>, With<Transform>)>,
+    interactables: Query<'w, 's, &'static Interactable>,
+}
+
+impl Interactables<'_, '_> {
+    pub fn get(&self, entity: Entity) -> Option<&Interactable> {
+        self.spatial
+            .contains(entity)
+            .then(|| self.interactables.get(entity).ok())
+            .flatten()
+    }
+
+    /// Nearest interactable to `position` that is within its own range from it.
+    pub fn nearest_in_range(&self, position: Vec3, except: Entity) -> Option<Entity> {
+        self.spatial
+            .within_radius(position, MAX_INTERACTION_RANGE)
+            .filter(|(entity, _)| *entity != except)
+            .filter_map(|(entity, other)| {
+                let distance = other.distance(position);
+                let interactable = self.interactables.get(entity).ok()?;
+                (distance <= interactable.range).then_some((entity, distance))
+            })
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(entity, _)| entity)
+    }
+
+    /// Nearest interactable to `position` regardless of its range, for example around cursor.
+    pub fn nearest(&self, position: Vec3, max_distance: f32) -> Option<Entity> {
+        self.spatial
+            .nearest(position, 1, max_distance)
+            .first()
+            .map(|(entity, _)| *entity)
+    }
+
+    pub fn in_range(&self, entity: Entity, position: Vec3) -> bool {
+        self.get(entity).is_some_and(|interactable| {
+            self.spatial
+                .within_radius(position, interactable.range)
+                .any(|(e, _)| e == entity)
+        })
+    }
+}
+
+fn update_interaction_focus(
+    mut focus: ResMut<InteractionFocus>,
+    interactables: Interactables,
+    player: Option<Single<(Entity, &Transform), With<Player>>>,
+) {
+    let nearest =
+        player.and_then(|player| interactables.nearest_in_range(player.1.translation, player.0));
+
+    if focus.0 != nearest {
+        focus.0 = nearest;
+    }
+}
+
+fn interact_with_focus(
+    mut events: EventWriter<InteractionEvent>,
+    focus: Res<InteractionFocus>,
+    input: Res<GameplayInput>,
+    interactables: Interactables,
+    player: Option<Single<Entity, With<Player>>>,
+) {
+    if !input.interact {
+        return;
+    }
+
+    let (Some(player), Some(target)) = (player, focus.0) else {
+        return;
+    };
+
+    if let Some(interactable) = interactables.get(target) {
+        events.send(InteractionEvent {
+            actor: *player,
+            target,
+            kind: interactable.kind,
+        });
+    }
+}
+
+fn pick_up_items(
+    mut commands: Commands,
+    mut events: EventReader<InteractionEvent>,
+    items: Query<(), (With<Item>, With<Transform>)>,
+) {
+    for event in events.read() {
+        if event.kind == InteractionKind::PickUp && items.contains(event.target) {
+            commands.queue(InsertItemCommand {
+                storage: event.actor,
+                item: event.target,
+            });
+        }
+    }
+}
+
+#[derive(Component)]
+struct InteractionPrompt;
+
+fn spawn_interaction_prompt(mut commands: Commands) {
+    commands.spawn((InteractionPrompt, Text::default(), Node {
+        position_type: PositionType::Absolute,
+        bottom: Val::Px(48.0),
+        left: Val::Percent(50.0),
+        ..default()
+    }));
+}
+
+fn update_interaction_prompt(
+    mut prompt: Single<&mut Text, With<InteractionPrompt>>,
+    focus: Res<InteractionFocus>,
+    interactables: Query<(&Interactable, &Name)>,
+    input: Res<GameplayInput>,
+    map: Res<InputMap>,
+) {
+    let text = focus
+        .0
+        .and_then(|entity| interactables.get(entity).ok())
+        .map(|(interactable, name)| {
+            // show the binding of the device that is actually used
+            let binding = map
+                .bindings(InputAction::Interact)
+                .iter()
+                .find(|b| b.device() == input.device)
+                .map_or("?".to_string(), |b| b.to_string());
+            format!("[{}] {} {}", binding, interactable.prompt, name)
+        })
+        .unwrap_or_default();
+
+    if prompt.0 != text {
+        prompt.0 = text;
+    }
+}
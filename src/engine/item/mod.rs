@@ -3,6 +3,10 @@ pub mod storage;
 use bevy::prelude::*;
 use storage::ItemStoragePlugin;
 
+use super::character::controller::Collider;
+use super::interaction::Interactable;
+use super::spatial::SpatialIndexed;
+
 pub struct ItemPlugin;
 
 impl Plugin for ItemPlugin {
@@ -46,11 +50,19 @@ fn validate_item_assumption(
 }
 
 #[derive(Component, Clone, Default, Reflect, Debug)]
-#[require(Name(|| Name::new("Item")), ItemDescription, ItemValue)]
+#[require(
+    Name(|| Name::new("Item")),
+    ItemDescription,
+    ItemValue,
+    Collider,
+    Interactable,
+    SpatialIndexed
+)]
 pub struct Item;
 
 #[derive(Component, Clone, Default, Reflect, Debug)]
 pub struct ItemDescription(pub String);
 
 #[derive(Component, Clone, Default, Reflect, Debug)]
+#[reflect(Component)]
 pub struct ItemValue(pub u16);
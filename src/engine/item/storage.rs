@@ -1,5 +1,6 @@
 use bevy::prelude::*;
 
+use crate::engine::character::controller::CharacterController;
 use crate::engine::item::Item;
 
 pub struct ItemStoragePlugin;
@@ -59,11 +60,17 @@ impl Command for DropItemCommand {
                 .is_some_and(|p| p.get() == self.storage)
         );
 
-        let transform = world
+        let mut transform = world
             .entity(self.storage)
             .get::<GlobalTransform>()
             .unwrap()
             .compute_transform();
+
+        // characters are positioned by capsule center, drop items at their feet
+        if let Some(controller) = world.entity(self.storage).get::<CharacterController>() {
+            transform.translation.y -= controller.half_height();
+        }
+
         world
             .entity_mut(self.item)
             .insert(transform)
@@ -1,49 +1,244 @@
 pub mod camera;
 pub mod character;
+pub mod config;
+pub mod debug_gizmos;
+pub mod headless;
 pub mod input;
+pub mod interaction;
 pub mod item;
+pub mod navigation;
+pub mod profiling;
 pub mod prototype;
+pub mod random;
+pub mod remote;
+pub mod replay;
+pub mod simulation;
+pub mod spatial;
+pub mod time_control;
 
 mod debug_console;
+mod inspector;
 
+use std::path::PathBuf;
+use std::time::Duration;
+
+use bevy::app::ScheduleRunnerPlugin;
 use bevy::diagnostic::{
     EntityCountDiagnosticsPlugin, FrameTimeDiagnosticsPlugin, LogDiagnosticsPlugin,
 };
 use bevy::ecs::system::RunSystemOnce;
+use bevy::log::LogPlugin;
 use bevy::prelude::*;
-use bevy_inspector_egui::DefaultInspectorConfigPlugin;
-use bevy_inspector_egui::quick::WorldInspectorPlugin;
+use bevy::reflect::Reflectable;
+use bevy::render::RenderPlugin;
+use bevy::render::settings::WgpuSettings;
+use bevy::time::TimeUpdateStrategy;
+use bevy::window::ExitCondition;
+use bevy::winit::WinitPlugin;
 use camera::GameCameraPlugin;
 use character::CharacterPlugin;
-use clap::{ArgAction, Parser};
+use character::faction::{FactionPlugin, FactionRelations};
+use clap::error::ErrorKind;
+use clap::{CommandFactory, Parser};
+use config::settings::{DebugSettings, SettingsPlugin, WindowSettings};
+use config::{Config, ConfigSection};
 use debug_console::DebugConsolePlugin;
+use debug_gizmos::{DebugGizmos, DebugGizmosPlugin};
+use headless::HeadlessPlugin;
 use input::GameInputPlugin;
+use inspector::InspectorPlugin;
+use interaction::InteractionPlugin;
 use item::ItemPlugin;
+use navigation::{NavigationDebug, NavigationPlugin};
+use profiling::{ChromeTraceFile, ProfilingPlugin, chrome_trace_layer};
 use prototype::{PrototypeId, PrototypeRegistry};
+use random::GameRng;
+use remote::{DEFAULT_REMOTE_PORT_TEXT, RemotePlugin};
+use replay::{DeterministicSimulation, RecordPlugin, Recording, ReplayPlugin};
+use simulation::SimulationPlugin;
+use spatial::SpatialIndexPlugin;
+use time_control::TimeControlPlugin;
+
+pub fn create_app<
+    CharacterId: PrototypeId,
+    ItemId: PrototypeId,
+    FactionId: PrototypeId + Reflectable + FromReflect,
+    CharacterMarker,
+    ItemMarker,
+    FactionMarker,
+>(
+    info: GameInfo,
+    create_character_registry: impl IntoSystem<(), PrototypeRegistry<CharacterId>, CharacterMarker>,
+    create_item_registry: impl IntoSystem<(), PrototypeRegistry<ItemId>, ItemMarker>,
+    create_faction_relations: impl IntoSystem<(), FactionRelations<FactionId>, FactionMarker>,
+) -> App {
+    create_app_with_args(
+        EngineArgs::parse(),
+        info,
+        create_character_registry,
+        create_item_registry,
+        create_faction_relations,
+    )
+}
+
+/// Same as [`create_app`], but with given command line instead of the one of the process.
+#[cfg(test)]
+pub fn create_test_app<
+    CharacterId: PrototypeId,
+    ItemId: PrototypeId,
+    FactionId: PrototypeId + Reflectable + FromReflect,
+    CharacterMarker,
+    ItemMarker,
+    FactionMarker,
+>(
+    args: &[&str],
+    info: GameInfo,
+    create_character_registry: impl IntoSystem<(), PrototypeRegistry<CharacterId>, CharacterMarker>,
+    create_item_registry: impl IntoSystem<(), PrototypeRegistry<ItemId>, ItemMarker>,
+    create_faction_relations: impl IntoSystem<(), FactionRelations<FactionId>, FactionMarker>,
+) -> App {
+    let args = std::iter::once(info.name).chain(args.iter().copied());
+    create_app_with_args(
+        EngineArgs::parse_from(args),
+        info,
+        create_character_registry,
+        create_item_registry,
+        create_faction_relations,
+    )
+}
 
-pub fn create_app<CharacterId: PrototypeId, ItemId: PrototypeId, CharacterMarker, ItemMarker>(
+fn create_app_with_args<
+    CharacterId: PrototypeId,
+    ItemId: PrototypeId,
+    FactionId: PrototypeId + Reflectable + FromReflect,
+    CharacterMarker,
+    ItemMarker,
+    FactionMarker,
+>(
+    mut args: EngineArgs,
     info: GameInfo,
     create_character_registry: impl IntoSystem<(), PrototypeRegistry<CharacterId>, CharacterMarker>,
     create_item_registry: impl IntoSystem<(), PrototypeRegistry<ItemId>, ItemMarker>,
+    create_faction_relations: impl IntoSystem<(), FactionRelations<FactionId>, FactionMarker>,
 ) -> App {
-    let args = EngineArgs::parse();
+    // logging is not set up yet, so the error is reported like invalid command line
+    let replay = args.replay_file.as_ref().map(|file| {
+        Recording::load(file).unwrap_or_else(|error| {
+            EngineArgs::command()
+                .error(ErrorKind::Io, format!("Cannot load replay: {}", error))
+                .exit()
+        })
+    });
+    if replay.is_some() {
+        // replay exits by itself after the last recorded simulation step
+        args.headless_frames = Some(u32::MAX);
+    }
+
+    let seed = replay
+        .as_ref()
+        .map(|r| r.seed)
+        .or(args.seed)
+        .unwrap_or_else(rand::random);
+    let timestep = args
+        .frame_time
+        .or(replay.as_ref().map(|r| r.timestep))
+        .unwrap_or(REPLAY_TIMESTEP);
+
+    let env_prefix = format!("{}_", info.name.to_uppercase().replace('-', "_"));
+    // logging is set up with the app, so errors are logged at startup
+    let mut config_errors = Vec::new();
+    let mut config = Config::new(&args.settings_file);
+    if let Err(error) = config.read_file() {
+        config_errors.push(format!("{}, defaults will be used", error));
+    }
+    config.read_env(&env_prefix);
+    for error in args.override_config(&mut config) {
+        config_errors.push(format!("Invalid --set option: {}, it is ignored", error));
+    }
+    let debug = config.section::<DebugSettings>().unwrap_or_else(|error| {
+        config_errors.push(format!("{}, defaults will be used", error));
+        default()
+    });
+    let window = config.section::<WindowSettings>().unwrap_or_else(|error| {
+        config_errors.push(format!("{}, defaults will be used", error));
+        default()
+    });
 
     let mut app = App::new();
     app.insert_resource(info);
-    app.add_plugins(DefaultPlugins.set(bevy::window::WindowPlugin {
-        primary_window: Some(Window {
-            title: info.name.to_string(),
-            ..default()
-        }),
+    app.insert_resource(GameRng::new(seed));
+    app.insert_resource(config);
+    app.insert_resource(debug.clone());
+    app.insert_resource(window.clone());
+    app.add_systems(Startup, move || {
+        for error in &config_errors {
+            error!("{}", error);
+        }
+    });
+
+    // read by the log plugin, which is built with the default plugins
+    if let Some(file) = &args.trace_file {
+        app.insert_resource(ChromeTraceFile(file.clone()));
+    }
+    let log = LogPlugin {
+        custom_layer: chrome_trace_layer,
         ..default()
-    }));
+    };
+
+    match args.headless_frames {
+        None => {
+            app.add_plugins(
+                DefaultPlugins
+                    .set(WindowPlugin {
+                        primary_window: Some(window.window(info.name.to_string())),
+                        ..default()
+                    })
+                    .set(log),
+            );
+        }
+        Some(frames) => {
+            app.add_plugins((
+                DefaultPlugins
+                    .set(WindowPlugin {
+                        primary_window: None,
+                        exit_condition: ExitCondition::DontExit,
+                        ..default()
+                    })
+                    .set(RenderPlugin {
+                        render_creation: WgpuSettings {
+                            backends: None,
+                            ..default()
+                        }
+                        .into(),
+                        ..default()
+                    })
+                    .set(log)
+                    .disable::<WinitPlugin>(),
+                ScheduleRunnerPlugin::run_loop(Duration::ZERO),
+                HeadlessPlugin { frames },
+            ));
+        }
+    }
 
     app.add_plugins((
+        SettingsPlugin,
         GameInputPlugin,
         GameCameraPlugin,
         CharacterPlugin,
+        DebugGizmosPlugin,
+        FactionPlugin::<FactionId>::default(),
+        InteractionPlugin,
         ItemPlugin,
+        NavigationPlugin,
+        SimulationPlugin,
+        SpatialIndexPlugin,
+        TimeControlPlugin,
     ));
+    app.add_plugins(ProfilingPlugin {
+        show_hud: debug.profiler,
+        headless: args.headless_frames.is_some(),
+        report_file: args.perf_report_file.clone(),
+    });
 
     let character_registry = app
         .world_mut()
@@ -57,18 +252,48 @@ pub fn create_app<CharacterId: PrototypeId, ItemId: PrototypeId, CharacterMarker
         .expect("Cannot initialize item registry");
     app.insert_resource(item_registry);
 
-    if args.show_game_version_overlay {
+    let faction_relations = app
+        .world_mut()
+        .run_system_once(create_faction_relations)
+        .expect("Cannot initialize faction relations");
+    app.insert_resource(faction_relations);
+
+    if let Some(file) = &args.record_file {
+        app.add_plugins(RecordPlugin {
+            file: file.clone(),
+            seed,
+            timestep,
+        });
+    }
+
+    if let Some(recording) = replay {
+        app.add_plugins(ReplayPlugin { recording });
+    }
+
+    if args.record_file.is_some() || args.replay_file.is_some() {
+        app.insert_resource(DeterministicSimulation);
+        app.insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f64(
+            timestep,
+        )));
+    }
+
+    if debug.version_overlay {
         app.add_systems(Startup, spawn_info_overlay);
     }
 
-    if args.enable_inspector {
-        app.add_plugins((
-            DefaultInspectorConfigPlugin,
-            WorldInspectorPlugin::default(),
-        ));
+    if debug.navigation_gizmos {
+        app.insert_resource(NavigationDebug { enabled: true });
     }
 
-    if args.enable_diagnostics {
+    app.insert_resource(DebugGizmos {
+        pickup_ranges: debug.pickup_gizmos,
+        npc_targets: debug.npc_gizmos,
+        camera: debug.camera_gizmos,
+        storage: debug.storage_gizmos,
+        health_bars: debug.health_gizmos,
+    });
+
+    if debug.diagnostics {
         app.add_plugins((
             LogDiagnosticsPlugin::default(),
             FrameTimeDiagnosticsPlugin,
@@ -76,50 +301,274 @@ pub fn create_app<CharacterId: PrototypeId, ItemId: PrototypeId, CharacterMarker
         ));
     }
 
-    if args.enable_console {
-        app.add_plugins(DebugConsolePlugin::<CharacterId, ItemId>::default());
+    if let Some(port) = debug.remote_port {
+        app.add_plugins(RemotePlugin::<CharacterId, ItemId>::new(port));
+    }
+
+    // inspector and console need a window to draw into, scripts run without it
+    if args.headless_frames.is_some() {
+        if !args.exec_files.is_empty() {
+            app.add_plugins(DebugConsolePlugin::<CharacterId, ItemId, FactionId>::new(
+                args.exec_files.clone(),
+                args.console_history_file.clone(),
+                true,
+            ));
+        }
+        return app;
+    }
+
+    if debug.inspector {
+        app.add_plugins(InspectorPlugin);
+    }
+
+    if debug.console || !args.exec_files.is_empty() {
+        app.add_plugins(DebugConsolePlugin::<CharacterId, ItemId, FactionId>::new(
+            args.exec_files.clone(),
+            args.console_history_file.clone(),
+            false,
+        ));
     }
 
     app
 }
 
+// debug switches without value enable the option, `-i false` disables it,
+// not given ones are taken from settings
 #[derive(Parser)]
 #[command(version)]
 struct EngineArgs {
+    #[arg(
+        long = "settings",
+        value_name = "FILE",
+        help = "Settings file, updated when settings are changed",
+        default_value = "settings.json"
+    )]
+    pub settings_file: PathBuf,
+
+    #[arg(
+        short = 's',
+        long = "set",
+        value_name = "SECTION.FIELD=VALUE",
+        help = "Override a setting for this run, for example graphics.quality=Low, can be repeated"
+    )]
+    pub settings: Vec<String>,
+
     #[arg(
         short = 'v',
         long = "version-overlay",
+        value_name = "BOOL",
         help = "Show game version overlay",
-        action = ArgAction::Set,
-        default_value_t = true,
+        num_args = 0..=1,
+        default_missing_value = "true"
     )]
-    pub show_game_version_overlay: bool,
+    pub show_game_version_overlay: Option<bool>,
 
     #[arg(
         short = 'i',
         long = "inspector",
+        value_name = "BOOL",
         help = "Enable world inspector",
-        default_value_t = false
+        num_args = 0..=1,
+        default_missing_value = "true"
     )]
-    pub enable_inspector: bool,
+    pub enable_inspector: Option<bool>,
 
     #[arg(
         short = 'l',
         long = "diagnostics-logger",
+        value_name = "BOOL",
         help = "Enable diagnostics logging",
-        default_value_t = false
+        num_args = 0..=1,
+        default_missing_value = "true"
+    )]
+    pub enable_diagnostics: Option<bool>,
+
+    #[arg(
+        long = "profiler",
+        value_name = "BOOL",
+        help = "Show performance HUD with frame times, entity counts and system timings",
+        num_args = 0..=1,
+        default_missing_value = "true"
     )]
-    pub enable_diagnostics: bool,
+    pub show_profiler: Option<bool>,
+
+    #[arg(
+        long = "trace-chrome",
+        value_name = "FILE",
+        help = "Write tracing spans and system timings to a Chrome trace file"
+    )]
+    pub trace_file: Option<PathBuf>,
+
+    #[arg(
+        long = "perf-report",
+        value_name = "FILE",
+        help = "Write performance summary as JSON on exit, for example of a headless run"
+    )]
+    pub perf_report_file: Option<PathBuf>,
 
     #[arg(
         short = 'c',
         long = "debug-console",
+        value_name = "BOOL",
         help = "Enable debug console",
-        default_value_t = false
+        num_args = 0..=1,
+        default_missing_value = "true"
+    )]
+    pub enable_console: Option<bool>,
+
+    #[arg(
+        long = "exec",
+        value_name = "FILE",
+        help = "Run console commands from a file at startup, can be repeated, works headless too"
+    )]
+    pub exec_files: Vec<PathBuf>,
+
+    #[arg(
+        long = "console-history",
+        value_name = "FILE",
+        help = "Console command history file, updated with every command",
+        default_value = "console_history.txt"
+    )]
+    pub console_history_file: PathBuf,
+
+    #[arg(
+        long = "remote",
+        value_name = "PORT",
+        help = "Serve remote debug protocol (JSON-RPC) on localhost port",
+        num_args = 0..=1,
+        default_missing_value = DEFAULT_REMOTE_PORT_TEXT
     )]
-    pub enable_console: bool,
+    pub remote_port: Option<u16>,
+
+    #[arg(
+        short = 'n',
+        long = "navigation-gizmos",
+        value_name = "BOOL",
+        help = "Show navigation grid and paths",
+        num_args = 0..=1,
+        default_missing_value = "true"
+    )]
+    pub show_navigation_gizmos: Option<bool>,
+
+    #[arg(
+        long = "pickup-gizmos",
+        value_name = "BOOL",
+        help = "Show pickup ranges around the player",
+        num_args = 0..=1,
+        default_missing_value = "true"
+    )]
+    pub show_pickup_gizmos: Option<bool>,
+
+    #[arg(
+        long = "npc-gizmos",
+        value_name = "BOOL",
+        help = "Show targets and paths of moving NPCs",
+        num_args = 0..=1,
+        default_missing_value = "true"
+    )]
+    pub show_npc_gizmos: Option<bool>,
+
+    #[arg(
+        long = "camera-gizmos",
+        value_name = "BOOL",
+        help = "Show camera target and direction",
+        num_args = 0..=1,
+        default_missing_value = "true"
+    )]
+    pub show_camera_gizmos: Option<bool>,
+
+    #[arg(
+        long = "storage-gizmos",
+        value_name = "BOOL",
+        help = "Show items inside of storages",
+        num_args = 0..=1,
+        default_missing_value = "true"
+    )]
+    pub show_storage_gizmos: Option<bool>,
+
+    #[arg(
+        long = "health-gizmos",
+        value_name = "BOOL",
+        help = "Show health bars of characters",
+        num_args = 0..=1,
+        default_missing_value = "true"
+    )]
+    pub show_health_gizmos: Option<bool>,
+
+    #[arg(
+        short = 'H',
+        long = "headless",
+        value_name = "FRAMES",
+        help = "Run without window and renderer for given number of frames, then log frame times"
+    )]
+    pub headless_frames: Option<u32>,
+
+    #[arg(
+        long = "record",
+        value_name = "FILE",
+        help = "Record gameplay input with fixed time step and save it on exit"
+    )]
+    pub record_file: Option<PathBuf>,
+
+    #[arg(
+        long = "replay",
+        value_name = "FILE",
+        help = "Replay recorded input headless, then log world checksum",
+        conflicts_with = "record_file"
+    )]
+    pub replay_file: Option<PathBuf>,
+
+    #[arg(
+        long = "seed",
+        value_name = "SEED",
+        help = "Seed of gameplay randomness, random by default"
+    )]
+    pub seed: Option<u64>,
+
+    #[arg(
+        long = "frame-time",
+        value_name = "SECONDS",
+        help = "Frame time of --record or --replay, replay gives the same result with any value"
+    )]
+    pub frame_time: Option<f64>,
+}
+
+impl EngineArgs {
+    /// Command line is the last settings layer, only options that were given are applied.
+    /// Returns errors of `--set` options that cannot be parsed.
+    fn override_config(&self, config: &mut Config) -> Vec<String> {
+        let debug = [
+            ("version_overlay", self.show_game_version_overlay),
+            ("inspector", self.enable_inspector),
+            ("diagnostics", self.enable_diagnostics),
+            ("profiler", self.show_profiler),
+            ("console", self.enable_console),
+            ("navigation_gizmos", self.show_navigation_gizmos),
+            ("pickup_gizmos", self.show_pickup_gizmos),
+            ("npc_gizmos", self.show_npc_gizmos),
+            ("camera_gizmos", self.show_camera_gizmos),
+            ("storage_gizmos", self.show_storage_gizmos),
+            ("health_gizmos", self.show_health_gizmos),
+        ];
+        for (field, value) in debug {
+            if let Some(value) = value {
+                config.set_override(DebugSettings::NAME, [field], value);
+            }
+        }
+        if let Some(port) = self.remote_port {
+            config.set_override(DebugSettings::NAME, ["remote_port"], Some(port));
+        }
+
+        self.settings
+            .iter()
+            .filter_map(|assignment| config.set_from_str(assignment).err())
+            .collect()
+    }
 }
 
+/// Default frame time of recorded sessions, fixed so timing of the session is reproducible.
+const REPLAY_TIMESTEP: f64 = 1.0 / 60.0;
+
 #[derive(Resource, Clone, Copy)]
 pub struct GameInfo {
     pub name: &'static str,
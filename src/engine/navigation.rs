@@ -0,0 +1,525 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::sync::Arc;
+
+use bevy::prelude::*;
+use bevy::render::primitives::Aabb;
+use bevy::tasks::{AsyncComputeTaskPool, Task, block_on, futures_lite};
+use bevy::utils::{HashMap, HashSet};
+use smart_default::SmartDefault;
+
+use crate::engine::replay::DeterministicSimulation;
+
+pub struct NavigationPlugin;
+
+impl Plugin for NavigationPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<NavObstacle>();
+        app.register_type::<NavPath>();
+        app.register_type::<NavigationDebug>();
+        app.init_resource::<NavGrid>();
+        app.init_resource::<NavigationDebug>();
+        app.add_systems(
+            FixedUpdate,
+            (
+                update_nav_grid,
+                revalidate_paths.run_if(resource_changed::<NavGrid>),
+                start_path_tasks,
+                poll_path_tasks,
+            )
+                .chain()
+                .in_set(NavigationSet),
+        );
+        app.add_systems(
+            Update,
+            draw_navigation_gizmos.run_if(|debug: Res<NavigationDebug>| debug.enabled),
+        );
+    }
+}
+
+/// Path finding systems, they run in [`FixedUpdate`] so paths are ready in the same steps
+/// regardless of the frame rate.
+#[derive(SystemSet, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct NavigationSet;
+
+/// Marks static or dynamic world geometry that characters have to walk around.
+/// Footprint is taken from [`Aabb`] of the entity mesh, so it must have one.
+#[derive(Component, Default, Clone, Reflect, Debug)]
+#[require(Transform)]
+pub struct NavObstacle;
+
+#[derive(Resource, Default, Reflect, Debug)]
+#[reflect(Resource)]
+pub struct NavigationDebug {
+    pub enabled: bool,
+}
+
+/// Walkability grid on XZ plane built from [`NavObstacle`] footprints.
+#[derive(Resource, Clone, SmartDefault, Debug)]
+pub struct NavGrid {
+    #[default(1.0)]
+    pub cell_size: f32,
+    #[default(500.0)]
+    pub half_extent: f32,
+    #[default(0.5)]
+    pub agent_radius: f32,
+    #[default(20_000)]
+    pub max_search_nodes: usize,
+    blocked: Arc<HashSet<IVec2>>,
+}
+
+impl NavGrid {
+    pub fn cell(&self, position: Vec3) -> IVec2 {
+        IVec2::new(
+            (position.x / self.cell_size).floor() as i32,
+            (position.z / self.cell_size).floor() as i32,
+        )
+    }
+
+    pub fn cell_center(&self, cell: IVec2, y: f32) -> Vec3 {
+        Vec3::new(
+            (cell.x as f32 + 0.5) * self.cell_size,
+            y,
+            (cell.y as f32 + 0.5) * self.cell_size,
+        )
+    }
+
+    pub fn is_walkable(&self, cell: IVec2) -> bool {
+        let limit = (self.half_extent / self.cell_size) as i32;
+        cell.x.abs() <= limit && cell.y.abs() <= limit && !self.blocked.contains(&cell)
+    }
+
+    /// Center of the walkable cell closest to `position`, among cells with centers
+    /// within `radius` of `around`.
+    pub fn nearest_walkable(&self, around: Vec3, radius: f32, position: Vec3) -> Option<Vec3> {
+        let min = self.cell(around - Vec3::splat(radius));
+        let max = self.cell(around + Vec3::splat(radius));
+        (min.x..=max.x)
+            .flat_map(|x| (min.y..=max.y).map(move |y| IVec2::new(x, y)))
+            .filter(|cell| self.is_walkable(*cell))
+            .map(|cell| self.cell_center(cell, position.y))
+            .filter(|center| center.xz().distance(around.xz()) <= radius)
+            .min_by(|a, b| {
+                let a = a.xz().distance_squared(position.xz());
+                let b = b.xz().distance_squared(position.xz());
+                a.total_cmp(&b)
+            })
+    }
+
+    /// Checks whether straight line between cells crosses only walkable cells.
+    /// Diagonal steps also need both cells next to the corner walkable, like in [`Self::find_path`],
+    /// so the line does not cut corners of blocked cells.
+    pub fn line_of_sight(&self, from: IVec2, to: IVec2) -> bool {
+        let delta = (to - from).abs();
+        let step = (to - from).signum();
+        let mut cell = from;
+        let mut error = delta.x - delta.y;
+
+        loop {
+            if !self.is_walkable(cell) {
+                return false;
+            }
+            if cell == to {
+                return true;
+            }
+
+            let error2 = error * 2;
+            let step_x = error2 > -delta.y;
+            let step_y = error2 < delta.x;
+            if step_x
+                && step_y
+                && (!self.is_walkable(cell + IVec2::new(step.x, 0))
+                    || !self.is_walkable(cell + IVec2::new(0, step.y)))
+            {
+                return false;
+            }
+            if step_x {
+                error -= delta.y;
+                cell.x += step.x;
+            }
+            if step_y {
+                error += delta.x;
+                cell.y += step.y;
+            }
+        }
+    }
+
+    /// A* search over 8-connected cells, returned waypoints are smoothed and do not contain start.
+    pub fn find_path(&self, start: Vec3, target: Vec3) -> Option<Vec<Vec3>> {
+        let start_cell = self.cell(start);
+        let target_cell = self.cell(target);
+
+        if !self.is_walkable(target_cell) {
+            return None;
+        }
+        if start_cell == target_cell || self.line_of_sight(start_cell, target_cell) {
+            return Some(vec![target]);
+        }
+
+        let heuristic = |cell: IVec2| {
+            let d = (target_cell - cell).abs();
+            (d.x.max(d.y) * 10 + d.x.min(d.y) * 4) as u32
+        };
+
+        let mut open = BinaryHeap::new();
+        let mut came_from = HashMap::<IVec2, IVec2>::new();
+        let mut costs = HashMap::<IVec2, u32>::new();
+        open.push(Reverse((heuristic(start_cell), start_cell.x, start_cell.y)));
+        costs.insert(start_cell, 0);
+
+        while let Some(Reverse((_, x, y))) = open.pop() {
+            let cell = IVec2::new(x, y);
+            if cell == target_cell {
+                return Some(self.build_path(&came_from, target_cell, start, target));
+            }
+            if came_from.len() >= self.max_search_nodes {
+                return None;
+            }
+
+            let cost = costs[&cell];
+            for offset in NEIGHBOURS {
+                let next = cell + offset;
+                if !self.is_walkable(next) {
+                    continue;
+                }
+
+                let diagonal = offset.x != 0 && offset.y != 0;
+                if diagonal
+                    && (!self.is_walkable(cell + IVec2::new(offset.x, 0))
+                        || !self.is_walkable(cell + IVec2::new(0, offset.y)))
+                {
+                    continue;
+                }
+
+                let next_cost = cost + if diagonal { 14 } else { 10 };
+                if costs.get(&next).is_none_or(|c| next_cost < *c) {
+                    costs.insert(next, next_cost);
+                    came_from.insert(next, cell);
+                    open.push(Reverse((next_cost + heuristic(next), next.x, next.y)));
+                }
+            }
+        }
+
+        None
+    }
+
+    fn build_path(
+        &self,
+        came_from: &HashMap<IVec2, IVec2>,
+        target_cell: IVec2,
+        start: Vec3,
+        target: Vec3,
+    ) -> Vec<Vec3> {
+        let mut cells = vec![target_cell];
+        while let Some(previous) = came_from.get(cells.last().unwrap()) {
+            cells.push(*previous);
+        }
+        cells.reverse();
+
+        // string pulling, skip every cell that is visible from the last kept one
+        let mut waypoints = Vec::new();
+        let mut anchor = cells[0];
+        for window in cells.windows(2).skip(1) {
+            if !self.line_of_sight(anchor, window[1]) {
+                anchor = window[0];
+                waypoints.push(self.cell_center(anchor, start.y));
+            }
+        }
+        waypoints.push(target);
+        waypoints
+    }
+
+    fn obstacle_cells(
+        &self,
+        transform: &GlobalTransform,
+        aabb: &Aabb,
+    ) -> impl Iterator<Item = IVec2> {
+        let (min, max) = (Vec3::from(aabb.min()), Vec3::from(aabb.max()));
+        let corners = [
+            Vec3::new(min.x, 0.0, min.z),
+            Vec3::new(min.x, 0.0, max.z),
+            Vec3::new(max.x, 0.0, min.z),
+            Vec3::new(max.x, 0.0, max.z),
+        ]
+        .map(|c| transform.transform_point(c));
+
+        let inflate = Vec3::new(self.agent_radius, 0.0, self.agent_radius);
+        let world_min = corners.into_iter().reduce(Vec3::min).unwrap() - inflate;
+        let world_max = corners.into_iter().reduce(Vec3::max).unwrap() + inflate;
+        let (min_cell, max_cell) = (self.cell(world_min), self.cell(world_max));
+
+        (min_cell.x..=max_cell.x)
+            .flat_map(move |x| (min_cell.y..=max_cell.y).map(move |y| IVec2::new(x, y)))
+    }
+}
+
+const NEIGHBOURS: [IVec2; 8] = [
+    IVec2::new(1, 0),
+    IVec2::new(-1, 0),
+    IVec2::new(0, 1),
+    IVec2::new(0, -1),
+    IVec2::new(1, 1),
+    IVec2::new(1, -1),
+    IVec2::new(-1, 1),
+    IVec2::new(-1, -1),
+];
+
+#[derive(Clone, Copy, Default, PartialEq, Eq, Reflect, Debug)]
+pub enum PathStatus {
+    #[default]
+    Idle,
+    Pending,
+    Ready,
+    Failed,
+}
+
+/// Path followed by a character, use [`NavPath::request`] to compute a new one in background.
+#[derive(Component, Clone, Default, Reflect, Debug)]
+pub struct NavPath {
+    pub waypoints: Vec<Vec3>,
+    pub status: PathStatus,
+    target: Option<Vec3>,
+    requested: bool,
+}
+
+impl NavPath {
+    pub fn request(&mut self, target: Vec3) {
+        self.waypoints.clear();
+        self.status = PathStatus::Pending;
+        self.target = Some(target);
+        self.requested = true;
+    }
+
+    pub fn clear(&mut self) {
+        self.waypoints.clear();
+        self.status = PathStatus::Idle;
+        self.target = None;
+        self.requested = false;
+    }
+
+    pub fn next_waypoint(&self) -> Option<Vec3> {
+        self.waypoints.first().copied()
+    }
+
+    pub fn advance(&mut self) {
+        if !self.waypoints.is_empty() {
+            self.waypoints.remove(0);
+        }
+    }
+
+    /// Returns velocity towards the next waypoint on XZ plane, reached waypoints are skipped.
+    /// Speed is limited so the waypoint is not overshot within `delta` seconds.
+    /// Returns `None` when there is nothing left to follow.
+    pub fn follow(&mut self, position: Vec3, speed: f32, delta: f32) -> Option<Vec3> {
+        while let Some(waypoint) = self.next_waypoint() {
+            let mut offset = waypoint - position;
+            offset.y = 0.0;
+            let distance = offset.length();
+            if distance >= WAYPOINT_TOLERANCE {
+                let speed = if delta > 0.0 {
+                    speed.min(distance / delta)
+                } else {
+                    speed
+                };
+                return Some(offset / distance * speed);
+            }
+
+            self.advance();
+        }
+
+        None
+    }
+}
+
+/// Distance at which waypoint counts as reached.
+const WAYPOINT_TOLERANCE: f32 = 0.25;
+
+#[derive(Component)]
+struct PathTask(Task<Option<Vec<Vec3>>>);
+
+fn update_nav_grid(
+    mut grid: ResMut<NavGrid>,
+    obstacles: Query<(&GlobalTransform, &Aabb), With<NavObstacle>>,
+    changed: Query<
+        (),
+        (
+            With<NavObstacle>,
+            Or<(Changed<GlobalTransform>, Changed<Aabb>)>,
+        ),
+    >,
+    mut removed: RemovedComponents<NavObstacle>,
+) {
+    if changed.is_empty() && removed.read().next().is_none() {
+        return;
+    }
+
+    let blocked = obstacles
+        .iter()
+        .flat_map(|(transform, aabb)| grid.obstacle_cells(transform, aabb).collect::<Vec<_>>())
+        .collect();
+    grid.blocked = Arc::new(blocked);
+}
+
+fn revalidate_paths(grid: Res<NavGrid>, mut paths: Query<(&GlobalTransform, &mut NavPath)>) {
+    for (transform, mut path) in paths.iter_mut() {
+        let Some(target) = path.target else {
+            continue;
+        };
+
+        let mut from = grid.cell(transform.translation());
+        let blocked = path.waypoints.iter().any(|waypoint| {
+            let to = grid.cell(*waypoint);
+            let visible = grid.line_of_sight(from, to);
+            from = to;
+            !visible
+        });
+
+        if blocked {
+            path.request(target);
+        }
+    }
+}
+
+fn start_path_tasks(
+    mut commands: Commands,
+    grid: Res<NavGrid>,
+    mut paths: Query<(Entity, &GlobalTransform, &mut NavPath), Changed<NavPath>>,
+) {
+    let pool = AsyncComputeTaskPool::get();
+    for (entity, transform, mut path) in paths.iter_mut() {
+        if !path.requested {
+            continue;
+        }
+        path.requested = false;
+
+        let grid = grid.clone();
+        let start = transform.translation();
+        let target = path.target.unwrap();
+        let task = pool.spawn(async move { grid.find_path(start, target) });
+        commands.entity(entity).insert(PathTask(task));
+    }
+}
+
+fn poll_path_tasks(
+    mut commands: Commands,
+    mut tasks: Query<(Entity, &mut PathTask, &mut NavPath)>,
+    deterministic: Option<Res<DeterministicSimulation>>,
+) {
+    for (entity, mut task, mut path) in tasks.iter_mut() {
+        // waiting for the task keeps results independent of thread timing
+        let result = if deterministic.is_some() {
+            block_on(&mut task.0)
+        } else {
+            let Some(result) = block_on(futures_lite::future::poll_once(&mut task.0)) else {
+                continue;
+            };
+            result
+        };
+
+        commands.entity(entity).remove::<PathTask>();
+        if path.requested {
+            // newer request is already waiting for its own task
+            continue;
+        }
+
+        match result {
+            Some(waypoints) => {
+                path.waypoints = waypoints;
+                path.status = PathStatus::Ready;
+            }
+            None => {
+                path.waypoints.clear();
+                path.status = PathStatus::Failed;
+            }
+        }
+    }
+}
+
+fn draw_navigation_gizmos(
+    mut gizmos: Gizmos,
+    grid: Res<NavGrid>,
+    paths: Query<(&GlobalTransform, &NavPath)>,
+) {
+    for cell in grid.blocked.iter() {
+        gizmos.rect(
+            Isometry3d::new(
+                grid.cell_center(*cell, 0.0),
+                Quat::from_rotation_x(std::f32::consts::FRAC_PI_2),
+            ),
+            Vec2::splat(grid.cell_size * 0.9),
+            Color::linear_rgb(1.0, 0.2, 0.2),
+        );
+    }
+
+    for (transform, path) in paths.iter() {
+        if path.waypoints.is_empty() {
+            continue;
+        }
+
+        gizmos.linestrip(
+            std::iter::once(transform.translation()).chain(path.waypoints.iter().copied()),
+            Color::linear_rgb(0.2, 0.6, 1.0),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn grid(blocked: &[(i32, i32)]) -> NavGrid {
+        NavGrid {
+            blocked: Arc::new(blocked.iter().map(|(x, y)| IVec2::new(*x, *y)).collect()),
+            ..default()
+        }
+    }
+
+    #[test]
+    fn line_of_sight() {
+        let cases = [
+            // (blocked cells, from, to, expected)
+            (vec![], (0, 0), (5, 3), true),
+            (vec![(0, 2)], (0, 0), (0, 4), false),
+            (vec![(2, 1)], (0, 0), (4, 0), true),
+            // obstacles touching diagonally, the line would pass through their corners
+            (vec![(1, 0), (0, 1)], (0, 0), (1, 1), false),
+            (vec![(1, 0), (0, 1)], (0, 0), (3, 3), false),
+            (vec![(2, 1), (1, 2)], (3, 3), (0, 0), false),
+            // single obstacle next to the diagonal
+            (vec![(1, 0)], (0, 0), (2, 2), false),
+            (vec![(2, 0)], (0, 0), (2, 2), true),
+        ];
+
+        for (blocked, from, to, expected) in cases {
+            let from = IVec2::new(from.0, from.1);
+            let to = IVec2::new(to.0, to.1);
+            assert_eq!(
+                grid(&blocked).line_of_sight(from, to),
+                expected,
+                "{:?} -> {:?} with {:?} blocked",
+                from,
+                to,
+                blocked
+            );
+        }
+    }
+
+    #[test]
+    fn path_goes_around_diagonal_obstacles() {
+        let grid = grid(&[(1, 0), (0, 1), (2, 1), (1, 2)]);
+        let path = grid
+            .find_path(grid.cell_center(IVec2::ZERO, 0.0), Vec3::new(2.5, 0.0, 2.5))
+            .unwrap();
+
+        let mut position = grid.cell_center(IVec2::ZERO, 0.0);
+        for waypoint in path {
+            assert!(
+                grid.line_of_sight(grid.cell(position), grid.cell(waypoint)),
+                "{} -> {} cuts through obstacle",
+                position,
+                waypoint
+            );
+            position = waypoint;
+        }
+    }
+}
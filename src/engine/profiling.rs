@@ -0,0 +1,527 @@
+use std::collections::VecDeque;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use bevy::ecs::schedule::{InternedScheduleLabel, InternedSystemSet, ScheduleLabel};
+use bevy::log::BoxedLayer;
+use bevy::log::tracing_subscriber::layer::{Context, Layer};
+use bevy::log::tracing_subscriber::registry::Registry;
+use bevy::prelude::*;
+use bevy::utils::tracing::field::{Field, Visit};
+use bevy::utils::tracing::span;
+use bevy_inspector_egui::bevy_egui::{EguiContexts, EguiPlugin};
+use bevy_inspector_egui::egui;
+use serde::Serialize;
+use serde_json::json;
+
+use super::character::npc::Npc;
+use super::character::{Character, CharacterSet};
+use super::headless::measure_headless_run;
+use super::input::GameplayInputSet;
+use super::item::Item;
+use super::navigation::NavigationSet;
+
+/// Frames shown in the frame time graph and used for averages of the HUD.
+const HUD_HISTORY: usize = 240;
+/// Frame time graph goes at least up to this, so small spikes do not look huge.
+const GRAPH_MIN_MS: f32 = 33.3;
+
+/// Frame time and gameplay system timing, shown in a HUD, exported to a Chrome trace
+/// (with [`chrome_trace_layer`]) and summarized in a report written on exit.
+pub struct ProfilingPlugin {
+    pub show_hud: bool,
+    /// HUD is not available without a window.
+    pub headless: bool,
+    pub report_file: Option<PathBuf>,
+}
+
+impl Plugin for ProfilingPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<ProfilerHud>();
+        app.init_resource::<FrameTimes>();
+        app.init_resource::<SystemTimings>();
+        app.add_systems(Last, record_frame_time);
+
+        // sets are measured from their start to their end, so systems running
+        // in parallel with them are included, previous sets are excluded by the ordering
+        add_probe(app, PreUpdate, GameplayInputSet, None, "input");
+        add_probe(
+            app,
+            RunFixedMainLoop,
+            RunFixedMainLoopSystem::FixedMainLoop,
+            None,
+            "simulation",
+        );
+        add_probe(app, FixedUpdate, NavigationSet, None, "navigation");
+        add_probe(
+            app,
+            FixedUpdate,
+            CharacterSet::Movement,
+            Some(NavigationSet.intern()),
+            "character movement",
+        );
+        add_probe(
+            app,
+            FixedUpdate,
+            CharacterSet::Avoidance,
+            Some(CharacterSet::Movement.intern()),
+            "avoidance",
+        );
+        add_probe(
+            app,
+            FixedUpdate,
+            CharacterSet::Physics,
+            Some(CharacterSet::Avoidance.intern()),
+            "character physics",
+        );
+
+        if let Some(file) = &self.report_file {
+            app.insert_resource(PerfReportFile(file.clone()));
+            app.add_systems(
+                Last,
+                write_perf_report
+                    .after(record_frame_time)
+                    .after(measure_headless_run)
+                    .run_if(on_event::<AppExit>),
+            );
+        }
+
+        if app.world().contains_resource::<ChromeTraceWriter>() {
+            app.add_systems(Last, finish_chrome_trace.run_if(on_event::<AppExit>));
+        }
+
+        if self.headless {
+            return;
+        }
+
+        if !app.is_plugin_added::<EguiPlugin>() {
+            app.add_plugins(EguiPlugin);
+        }
+        app.insert_resource(ProfilerHud {
+            visible: self.show_hud,
+        });
+        app.add_systems(
+            Update,
+            profiler_hud.run_if(|hud: Res<ProfilerHud>| hud.visible),
+        );
+    }
+}
+
+#[derive(Resource, Default, Reflect, Debug)]
+#[reflect(Resource)]
+pub struct ProfilerHud {
+    pub visible: bool,
+}
+
+/// Real frame times in milliseconds, the first frame with startup systems is skipped.
+#[derive(Resource, Default)]
+struct FrameTimes {
+    recent: VecDeque<f32>,
+    /// Every frame of the run, for percentiles of the report, kept only when it is written.
+    all: Vec<f32>,
+}
+
+/// Time spent in gameplay system sets, sets of [`FixedUpdate`] can run several times
+/// per frame, so their time in the frame is summed.
+#[derive(Resource, Default)]
+struct SystemTimings(Vec<SystemTiming>);
+
+struct SystemTiming {
+    name: &'static str,
+    started: Option<Instant>,
+    frame: Duration,
+    recent: VecDeque<Duration>,
+    total: Duration,
+    worst: Duration,
+    frames: u32,
+}
+
+impl SystemTiming {
+    fn recent_average(&self) -> Duration {
+        let frames = self.recent.len().max(1) as u32;
+        self.recent.iter().sum::<Duration>() / frames
+    }
+}
+
+fn add_probe(
+    app: &mut App,
+    schedule: impl ScheduleLabel,
+    set: impl SystemSet,
+    after: Option<InternedSystemSet>,
+    name: &'static str,
+) {
+    let mut timings = app.world_mut().resource_mut::<SystemTimings>();
+    let index = timings.0.len();
+    timings.0.push(SystemTiming {
+        name,
+        started: None,
+        frame: Duration::ZERO,
+        recent: VecDeque::new(),
+        total: Duration::ZERO,
+        worst: Duration::ZERO,
+        frames: 0,
+    });
+
+    let schedule: InternedScheduleLabel = schedule.intern();
+    let set = set.intern();
+    let start = move |mut timings: ResMut<SystemTimings>| {
+        timings.0[index].started = Some(Instant::now());
+    };
+    let end = move |mut timings: ResMut<SystemTimings>, trace: Option<Res<ChromeTraceWriter>>| {
+        let timing = &mut timings.0[index];
+        let Some(started) = timing.started.take() else {
+            return;
+        };
+
+        let elapsed = started.elapsed();
+        timing.frame += elapsed;
+        if let Some(trace) = trace {
+            trace.complete(name, started, elapsed);
+        }
+    };
+
+    match after {
+        Some(after) => app.add_systems(schedule, start.before(set).after(after)),
+        None => app.add_systems(schedule, start.before(set)),
+    };
+    app.add_systems(schedule, end.after(set));
+}
+
+fn record_frame_time(
+    mut frames: ResMut<FrameTimes>,
+    mut timings: ResMut<SystemTimings>,
+    time: Res<Time<Real>>,
+    report: Option<Res<PerfReportFile>>,
+) {
+    if time.delta() == Duration::ZERO {
+        return;
+    }
+
+    let milliseconds = time.delta_secs() * 1000.0;
+    frames.recent.push_back(milliseconds);
+    if frames.recent.len() > HUD_HISTORY {
+        frames.recent.pop_front();
+    }
+    if report.is_some() {
+        frames.all.push(milliseconds);
+    }
+
+    for timing in timings.0.iter_mut() {
+        let frame = std::mem::take(&mut timing.frame);
+        timing.recent.push_back(frame);
+        if timing.recent.len() > HUD_HISTORY {
+            timing.recent.pop_front();
+        }
+        timing.total += frame;
+        timing.worst = timing.worst.max(frame);
+        timing.frames += 1;
+    }
+}
+
+fn profiler_hud(
+    mut contexts: EguiContexts,
+    frames: Res<FrameTimes>,
+    timings: Res<SystemTimings>,
+    entities: Query<()>,
+    characters: Query<(), With<Character>>,
+    npcs: Query<(), With<Npc>>,
+    items: Query<(), With<Item>>,
+) {
+    let Some(ctx) = contexts.try_ctx_mut() else {
+        return;
+    };
+
+    egui::Window::new("Performance")
+        .default_pos([8.0, 8.0])
+        .resizable(false)
+        .show(ctx, |ui| {
+            let average = frames.recent.iter().sum::<f32>() / frames.recent.len().max(1) as f32;
+            let worst = frames.recent.iter().copied().fold(0.0, f32::max);
+            ui.monospace(format!(
+                "{:.1} fps, {:.2} ms, worst {:.2} ms",
+                1000.0 / average.max(f32::EPSILON),
+                average,
+                worst
+            ));
+            frame_time_graph(ui, &frames.recent);
+
+            ui.separator();
+            ui.monospace(format!("entities   {:>6}", entities.iter().count()));
+            ui.monospace(format!("characters {:>6}", characters.iter().count()));
+            ui.monospace(format!("npcs       {:>6}", npcs.iter().count()));
+            ui.monospace(format!("items      {:>6}", items.iter().count()));
+
+            ui.separator();
+            for timing in timings.0.iter() {
+                ui.monospace(format!(
+                    "{:<18} {:>6.3} ms",
+                    timing.name,
+                    timing.recent_average().as_secs_f64() * 1000.0
+                ));
+            }
+        });
+}
+
+fn frame_time_graph(ui: &mut egui::Ui, frames: &VecDeque<f32>) {
+    let (rect, _) = ui.allocate_exact_size(egui::vec2(240.0, 60.0), egui::Sense::hover());
+    let painter = ui.painter_at(rect);
+    painter.rect_filled(rect, 0.0, egui::Color32::from_black_alpha(120));
+
+    let top = frames.iter().copied().fold(GRAPH_MIN_MS, f32::max);
+    let y = |milliseconds: f32| rect.bottom() - rect.height() * milliseconds / top;
+
+    // 60 fps budget
+    painter.hline(
+        rect.x_range(),
+        y(1000.0 / 60.0),
+        egui::Stroke::new(1.0, egui::Color32::DARK_GREEN),
+    );
+
+    let step = rect.width() / HUD_HISTORY as f32;
+    let points = frames
+        .iter()
+        .enumerate()
+        .map(|(index, milliseconds)| {
+            egui::pos2(rect.left() + index as f32 * step, y(*milliseconds))
+        })
+        .collect();
+    painter.add(egui::Shape::line(
+        points,
+        egui::Stroke::new(1.0, egui::Color32::LIGHT_GREEN),
+    ));
+}
+
+#[derive(Resource)]
+struct PerfReportFile(PathBuf);
+
+#[derive(Serialize)]
+struct PerfReport {
+    frames: usize,
+    average_frame_ms: f32,
+    p99_frame_ms: f32,
+    worst_frame_ms: f32,
+    entities: usize,
+    characters: usize,
+    npcs: usize,
+    items: usize,
+    systems: Vec<SystemReport>,
+}
+
+#[derive(Serialize)]
+struct SystemReport {
+    name: &'static str,
+    average_ms: f64,
+    worst_ms: f64,
+}
+
+fn write_perf_report(
+    file: Res<PerfReportFile>,
+    frames: Res<FrameTimes>,
+    timings: Res<SystemTimings>,
+    entities: Query<()>,
+    characters: Query<(), With<Character>>,
+    npcs: Query<(), With<Npc>>,
+    items: Query<(), With<Item>>,
+) {
+    let mut sorted = frames.all.clone();
+    sorted.sort_by(f32::total_cmp);
+    let percentile = |fraction: f32| {
+        let index = ((sorted.len() as f32 - 1.0) * fraction).round() as usize;
+        sorted.get(index).copied().unwrap_or_default()
+    };
+
+    let report = PerfReport {
+        frames: sorted.len(),
+        average_frame_ms: sorted.iter().sum::<f32>() / sorted.len().max(1) as f32,
+        p99_frame_ms: percentile(0.99),
+        worst_frame_ms: sorted.last().copied().unwrap_or_default(),
+        entities: entities.iter().count(),
+        characters: characters.iter().count(),
+        npcs: npcs.iter().count(),
+        items: items.iter().count(),
+        systems: timings
+            .0
+            .iter()
+            .map(|timing| SystemReport {
+                name: timing.name,
+                average_ms: timing.total.as_secs_f64() * 1000.0 / timing.frames.max(1) as f64,
+                worst_ms: timing.worst.as_secs_f64() * 1000.0,
+            })
+            .collect(),
+    };
+
+    let result = serde_json::to_string_pretty(&report)
+        .map_err(|e| e.to_string())
+        .and_then(|content| fs::write(&file.0, content).map_err(|e| e.to_string()));
+    match result {
+        Ok(()) => info!("Performance report written to '{}'", file.0.display()),
+        Err(error) => error!("Cannot write '{}': {}", file.0.display(), error),
+    }
+}
+
+/// Path of the Chrome trace, has to be inserted before [`LogPlugin`](bevy::log::LogPlugin)
+/// is built, so [`chrome_trace_layer`] can find it.
+#[derive(Resource)]
+pub struct ChromeTraceFile(pub PathBuf);
+
+/// Events in Chrome trace format (`chrome://tracing` or Perfetto), shared by the tracing
+/// layer and system timing probes.
+#[derive(Resource, Clone)]
+struct ChromeTraceWriter(Arc<Mutex<ChromeTrace>>);
+
+struct ChromeTrace {
+    writer: BufWriter<File>,
+    start: Instant,
+    empty: bool,
+}
+
+impl ChromeTrace {
+    fn create(path: &Path) -> io::Result<Self> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        writer.write_all(b"[\n")?;
+        Ok(Self {
+            writer,
+            start: Instant::now(),
+            empty: true,
+        })
+    }
+
+    fn write(&mut self, event: serde_json::Value) {
+        // trace is best effort, failed writes only make it shorter
+        let separator: &[u8] = if self.empty { b"" } else { b",\n" };
+        self.empty = false;
+        let _ = self.writer.write_all(separator);
+        let _ = serde_json::to_writer(&mut self.writer, &event);
+    }
+
+    fn microseconds(&self, instant: Instant) -> f64 {
+        instant.saturating_duration_since(self.start).as_secs_f64() * 1e6
+    }
+}
+
+impl ChromeTraceWriter {
+    fn complete(&self, name: &str, started: Instant, duration: Duration) {
+        let mut trace = self.0.lock().expect("Chrome trace is not poisoned");
+        let event = json!({
+            "name": name,
+            "cat": "gameplay",
+            "ph": "X",
+            "ts": trace.microseconds(started),
+            "dur": duration.as_secs_f64() * 1e6,
+            "pid": 1,
+            "tid": thread_id(),
+        });
+        trace.write(event);
+    }
+
+    fn span(&self, name: &str, phase: &str) {
+        let mut trace = self.0.lock().expect("Chrome trace is not poisoned");
+        let event = json!({
+            "name": name,
+            "ph": phase,
+            "ts": trace.microseconds(Instant::now()),
+            "pid": 1,
+            "tid": thread_id(),
+        });
+        trace.write(event);
+    }
+}
+
+fn thread_id() -> u64 {
+    static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+    thread_local! {
+        static ID: u64 = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+    }
+    ID.with(|id| *id)
+}
+
+/// Custom layer of [`LogPlugin`](bevy::log::LogPlugin) writing spans into the Chrome trace
+/// of [`ChromeTraceFile`]. Build with `--features bevy/trace` to get spans of every system.
+pub fn chrome_trace_layer(app: &mut App) -> Option<BoxedLayer> {
+    let path = app.world().get_resource::<ChromeTraceFile>()?.0.clone();
+    match ChromeTrace::create(&path) {
+        Ok(trace) => {
+            let writer = ChromeTraceWriter(Arc::new(Mutex::new(trace)));
+            app.insert_resource(writer.clone());
+            Some(Box::new(ChromeTraceLayer(writer)))
+        }
+        Err(error) => {
+            // logging is not set up yet
+            eprintln!("Cannot create '{}': {}", path.display(), error);
+            None
+        }
+    }
+}
+
+fn finish_chrome_trace(writer: Res<ChromeTraceWriter>) {
+    let mut trace = writer.0.lock().expect("Chrome trace is not poisoned");
+    let result = trace
+        .writer
+        .write_all(b"\n]\n")
+        .and_then(|_| trace.writer.flush());
+    if let Err(error) = result {
+        error!("Cannot write Chrome trace: {}", error);
+    }
+}
+
+struct ChromeTraceLayer(ChromeTraceWriter);
+
+/// Span name shown in the trace, system spans get the name of their system.
+struct SpanName(String);
+
+/// Finds the `name` field, which Bevy uses for names of systems.
+struct NameVisitor(Option<String>);
+
+impl Visit for NameVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "name" {
+            self.0 = Some(value.to_string());
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        if field.name() == "name" {
+            self.0 = Some(format!("{:?}", value));
+        }
+    }
+}
+
+impl Layer<Registry> for ChromeTraceLayer {
+    fn on_new_span(
+        &self,
+        attributes: &span::Attributes<'_>,
+        id: &span::Id,
+        context: Context<'_, Registry>,
+    ) {
+        let mut visitor = NameVisitor(None);
+        attributes.record(&mut visitor);
+        let name = match visitor.0 {
+            Some(name) => format!("{} {}", attributes.metadata().name(), name),
+            None => attributes.metadata().name().to_string(),
+        };
+
+        if let Some(span) = context.span(id) {
+            span.extensions_mut().insert(SpanName(name));
+        }
+    }
+
+    fn on_enter(&self, id: &span::Id, context: Context<'_, Registry>) {
+        if let Some(span) = context.span(id) {
+            if let Some(name) = span.extensions().get::<SpanName>() {
+                self.0.span(&name.0, "B");
+            }
+        }
+    }
+
+    fn on_exit(&self, id: &span::Id, context: Context<'_, Registry>) {
+        if let Some(span) = context.span(id) {
+            if let Some(name) = span.extensions().get::<SpanName>() {
+                self.0.span(&name.0, "E");
+            }
+        }
+    }
+}
@@ -22,6 +22,10 @@ impl<T: PrototypeId> PrototypeInstance<T> {
     }
 }
 
+/// Prototype id as text, for code that does not know the id type (like console selectors).
+#[derive(Component, Clone, Debug)]
+pub struct PrototypeName(pub String);
+
 #[derive(Resource, Constructor)]
 pub struct PrototypeRegistry<T: PrototypeId>(HashMap<T, Box<dyn PrototypeBundle<T>>>);
 
@@ -34,6 +38,10 @@ impl<T: PrototypeId> PrototypeRegistry<T> {
         prototype.spawn(id, commands)
     }
 
+    pub fn ids(&self) -> impl Iterator<Item = T> + '_ {
+        self.0.keys().copied()
+    }
+
     pub fn spawn_at(&self, id: T, transform: Transform, commands: &mut Commands) -> Entity {
         let entity = self.spawn(id, commands);
         commands.entity(entity).insert(transform);
@@ -47,6 +55,12 @@ pub trait PrototypeBundle<Id: PrototypeId>: Send + Sync {
 
 impl<T: Bundle + Clone, Id: PrototypeId> PrototypeBundle<Id> for T {
     fn spawn(&self, id: Id, commands: &mut Commands) -> Entity {
-        commands.spawn((PrototypeInstance(id), self.clone())).id()
+        commands
+            .spawn((
+                PrototypeInstance(id),
+                PrototypeName(id.to_string()),
+                self.clone(),
+            ))
+            .id()
     }
 }
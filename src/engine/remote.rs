@@ -0,0 +1,619 @@
+use std::io::{self, BufRead, BufReader, Write};
+use std::marker::PhantomData;
+use std::net::{Ipv4Addr, TcpListener, TcpStream};
+use std::sync::Mutex;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+
+use bevy::core::FrameCount;
+use bevy::prelude::*;
+use bevy::reflect::GetPath;
+use bevy::reflect::serde::{TypedReflectDeserializer, TypedReflectSerializer};
+use serde::de::{DeserializeOwned, DeserializeSeed};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+
+use super::debug_console::{reflect_component, split_component_path};
+use super::prototype::{PrototypeId, PrototypeInstance, PrototypeRegistry};
+use super::time_control::TimeControl;
+
+/// Port of `--remote` without value, same as of Bevy Remote Protocol.
+pub const DEFAULT_REMOTE_PORT_TEXT: &str = "15702";
+
+const PARSE_ERROR: i32 = -32700;
+const METHOD_NOT_FOUND: i32 = -32601;
+const INVALID_PARAMS: i32 = -32602;
+/// Request was valid, but the world could not do it (like missing entity).
+const ENGINE_ERROR: i32 = -32000;
+
+/// Serves JSON-RPC 2.0 requests on a localhost TCP port, one JSON object per line,
+/// so external tools and test harnesses can drive a running game. Methods:
+/// - `prototype.ids {kind}`, `prototype.list {kind, id?}`,
+///   `prototype.spawn {kind, id, translation?}`, where `kind` is `character` or `item`,
+/// - `entity.despawn {entity}`,
+/// - `component.list {entity}`, `component.get {entity, path}`,
+///   `component.set {entity, path, value}`, where `path` is like `Health.current`,
+/// - `frame.step {frames?}`, replies after given number of frames, paused time is stepped
+///   in each of them.
+///
+/// Entities are numbers returned by `prototype.list` and `prototype.spawn`.
+pub struct RemotePlugin<CharacterId: PrototypeId, ItemId: PrototypeId> {
+    port: u16,
+    phantoms: PhantomData<(CharacterId, ItemId)>,
+}
+
+impl<CharacterId: PrototypeId, ItemId: PrototypeId> RemotePlugin<CharacterId, ItemId> {
+    pub fn new(port: u16) -> Self {
+        Self {
+            port,
+            phantoms: PhantomData,
+        }
+    }
+}
+
+impl<CharacterId: PrototypeId, ItemId: PrototypeId> Plugin for RemotePlugin<CharacterId, ItemId> {
+    fn build(&self, app: &mut App) {
+        // only local tools can connect, the protocol has no authentication
+        let listener = match TcpListener::bind((Ipv4Addr::LOCALHOST, self.port)) {
+            Ok(listener) => listener,
+            Err(error) => {
+                error!(
+                    "Cannot start remote server on port {}: {}",
+                    self.port, error
+                );
+                return;
+            }
+        };
+        info!("Remote server listening on port {}", self.port);
+
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || accept_connections(listener, sender));
+
+        app.insert_resource(RemoteRequests(Mutex::new(receiver)));
+        app.init_resource::<PendingSteps>();
+        app.add_systems(
+            Last,
+            (finish_steps, process_requests::<CharacterId, ItemId>).chain(),
+        );
+    }
+}
+
+#[derive(Deserialize)]
+struct Request {
+    #[serde(default)]
+    id: Value,
+    method: String,
+    #[serde(default)]
+    params: Value,
+}
+
+#[derive(Serialize)]
+struct Response {
+    jsonrpc: &'static str,
+    id: Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    result: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<RpcError>,
+}
+
+impl Response {
+    fn new(id: Value, result: Result<Value, RpcError>) -> Self {
+        let (result, error) = match result {
+            Ok(result) => (Some(result), None),
+            Err(error) => (None, Some(error)),
+        };
+        Self {
+            jsonrpc: "2.0",
+            id,
+            result,
+            error,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct RpcError {
+    code: i32,
+    message: String,
+}
+
+impl RpcError {
+    fn new(code: i32, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+/// Request with the channel of its connection, which waits for the response.
+struct RemoteRequest {
+    request: Request,
+    response: Sender<Response>,
+}
+
+/// Requests of all connections, received on their own threads.
+#[derive(Resource)]
+struct RemoteRequests(Mutex<Receiver<RemoteRequest>>);
+
+#[derive(Resource, Default)]
+struct PendingSteps(Vec<PendingStep>);
+
+struct PendingStep {
+    frames: u32,
+    id: Value,
+    response: Sender<Response>,
+}
+
+fn accept_connections(listener: TcpListener, requests: Sender<RemoteRequest>) {
+    for stream in listener.incoming() {
+        let Ok(stream) = stream else {
+            continue;
+        };
+
+        let requests = requests.clone();
+        thread::spawn(move || {
+            if let Err(error) = serve_connection(stream, requests) {
+                warn!("Remote connection closed: {}", error);
+            }
+        });
+    }
+}
+
+fn serve_connection(stream: TcpStream, requests: Sender<RemoteRequest>) -> io::Result<()> {
+    let mut writer = stream.try_clone()?;
+    for line in BufReader::new(stream).lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let response = match serde_json::from_str::<Request>(&line) {
+            Ok(request) => {
+                let (sender, receiver) = mpsc::channel();
+                let request = RemoteRequest {
+                    request,
+                    response: sender,
+                };
+                // either side is gone when the app exits
+                if requests.send(request).is_err() {
+                    break;
+                }
+                let Ok(response) = receiver.recv() else {
+                    break;
+                };
+                response
+            }
+            Err(error) => Response::new(
+                Value::Null,
+                Err(RpcError::new(PARSE_ERROR, error.to_string())),
+            ),
+        };
+
+        serde_json::to_writer(&mut writer, &response)?;
+        writer.write_all(b"\n")?;
+    }
+    Ok(())
+}
+
+fn finish_steps(mut pending: ResMut<PendingSteps>, frame: Res<FrameCount>) {
+    pending.0.retain_mut(|step| {
+        step.frames -= 1;
+        if step.frames > 0 {
+            return true;
+        }
+
+        let result = Ok(json!({ "frame": frame.0 }));
+        // client may have disconnected in the meantime
+        let _ = step.response.send(Response::new(step.id.take(), result));
+        false
+    });
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum PrototypeKind {
+    Character,
+    Item,
+}
+
+#[derive(Deserialize)]
+struct KindParams {
+    kind: PrototypeKind,
+}
+
+#[derive(Deserialize)]
+struct ListParams {
+    id: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct SpawnParams {
+    id: String,
+    #[serde(default)]
+    translation: Vec3,
+}
+
+#[derive(Deserialize)]
+struct EntityParams {
+    entity: Entity,
+}
+
+#[derive(Deserialize)]
+struct GetParams {
+    entity: Entity,
+    path: String,
+}
+
+#[derive(Deserialize)]
+struct SetParams {
+    entity: Entity,
+    path: String,
+    value: Value,
+}
+
+#[derive(Deserialize)]
+struct StepParams {
+    #[serde(default)]
+    frames: u32,
+}
+
+fn process_requests<CharacterId: PrototypeId, ItemId: PrototypeId>(world: &mut World) {
+    let requests = world
+        .resource::<RemoteRequests>()
+        .0
+        .lock()
+        .expect("Remote requests are not shared with other threads")
+        .try_iter()
+        .collect::<Vec<_>>();
+
+    for RemoteRequest { request, response } in requests {
+        let Request { id, method, params } = request;
+
+        if method == "frame.step" {
+            match parse_params::<StepParams>(params) {
+                Ok(StepParams { frames }) => {
+                    // paused simulation runs a step in each of the frames
+                    if world.resource::<Time<Virtual>>().is_paused() {
+                        world.resource_scope(|world, mut control: Mut<TimeControl>| {
+                            control.step(&mut world.resource_mut(), frames.max(1));
+                        });
+                    }
+                    world.resource_mut::<PendingSteps>().0.push(PendingStep {
+                        frames: frames.max(1),
+                        id,
+                        response,
+                    });
+                }
+                Err(error) => {
+                    let _ = response.send(Response::new(id, Err(error)));
+                }
+            }
+            continue;
+        }
+
+        let result = match method.as_str() {
+            "prototype.ids" | "prototype.list" | "prototype.spawn" => parse_params::<KindParams>(
+                params.clone(),
+            )
+            .and_then(|KindParams { kind }| match kind {
+                PrototypeKind::Character => prototype_method::<CharacterId>(world, &method, params),
+                PrototypeKind::Item => prototype_method::<ItemId>(world, &method, params),
+            }),
+            "entity.despawn" => parse_params(params).and_then(|p| despawn(world, p)),
+            "component.list" => parse_params(params).and_then(|p| list_components(world, p)),
+            "component.get" => parse_params(params).and_then(|p| get_component(world, p)),
+            "component.set" => parse_params(params).and_then(|p| set_component(world, p)),
+            _ => Err(RpcError::new(
+                METHOD_NOT_FOUND,
+                format!("Unknown method '{}'", method),
+            )),
+        };
+        let _ = response.send(Response::new(id, result));
+    }
+}
+
+fn parse_params<T: DeserializeOwned>(params: Value) -> Result<T, RpcError> {
+    // methods without required params can be called without them
+    let params = match params {
+        Value::Null => json!({}),
+        params => params,
+    };
+    serde_json::from_value(params).map_err(|e| RpcError::new(INVALID_PARAMS, e.to_string()))
+}
+
+fn prototype_method<T: PrototypeId>(
+    world: &mut World,
+    method: &str,
+    params: Value,
+) -> Result<Value, RpcError> {
+    match method {
+        "prototype.ids" => {
+            let registry = world.resource::<PrototypeRegistry<T>>();
+            let mut ids = registry.ids().map(|id| id.to_string()).collect::<Vec<_>>();
+            ids.sort();
+            Ok(json!(ids))
+        }
+        "prototype.list" => {
+            let ListParams { id } = parse_params(params)?;
+            let id = id.map(|id| parse_id::<T>(world, &id)).transpose()?;
+
+            let mut query = world.query::<(Entity, Option<&Name>, &PrototypeInstance<T>)>();
+            let instances = query
+                .iter(world)
+                .filter(|(_, _, prototype)| id.is_none_or(|id| prototype.id() == id))
+                .map(|(entity, name, prototype)| {
+                    json!({
+                        "entity": entity,
+                        "name": name.map(Name::as_str),
+                        "id": prototype.id().to_string(),
+                    })
+                })
+                .collect::<Vec<_>>();
+            Ok(json!(instances))
+        }
+        "prototype.spawn" => {
+            let SpawnParams { id, translation } = parse_params(params)?;
+            let id = parse_id::<T>(world, &id)?;
+
+            let entity = world.resource_scope(|world, registry: Mut<PrototypeRegistry<T>>| {
+                let transform = Transform::from_translation(translation);
+                registry.spawn_at(id, transform, &mut world.commands())
+            });
+            world.flush();
+            Ok(json!(entity))
+        }
+        _ => unreachable!("Only prototype methods are passed here"),
+    }
+}
+
+fn parse_id<T: PrototypeId>(world: &World, input: &str) -> Result<T, RpcError> {
+    let registry = world.resource::<PrototypeRegistry<T>>();
+    T::from_str(input)
+        .ok()
+        .filter(|id| registry.ids().any(|known| known == *id))
+        .ok_or_else(|| RpcError::new(INVALID_PARAMS, format!("Cannot parse id '{}'", input)))
+}
+
+fn despawn(world: &mut World, EntityParams { entity }: EntityParams) -> Result<Value, RpcError> {
+    let entity_mut = world.get_entity_mut(entity).map_err(|_| missing(entity))?;
+    entity_mut.despawn_recursive();
+    Ok(Value::Null)
+}
+
+fn list_components(world: &mut World, params: EntityParams) -> Result<Value, RpcError> {
+    let registry = world.resource::<AppTypeRegistry>().read();
+    let entity_ref = world
+        .get_entity(params.entity)
+        .map_err(|_| missing(params.entity))?;
+
+    let mut names = entity_ref
+        .archetype()
+        .components()
+        .filter_map(|id| world.components().get_info(id)?.type_id())
+        .filter_map(|type_id| registry.get(type_id))
+        .filter(|registration| registration.data::<ReflectComponent>().is_some())
+        .map(|registration| registration.type_info().type_path_table().short_path())
+        .collect::<Vec<_>>();
+    names.sort();
+    Ok(json!(names))
+}
+
+fn get_component(world: &mut World, params: GetParams) -> Result<Value, RpcError> {
+    let GetParams { entity, path } = params;
+    let registry = world.resource::<AppTypeRegistry>().read();
+    let (name, field) = split_component_path(&path);
+    let component =
+        reflect_component(&registry, name).map_err(|e| RpcError::new(INVALID_PARAMS, e))?;
+    let entity_ref = world.get_entity(entity).map_err(|_| missing(entity))?;
+
+    let value = component
+        .reflect(entity_ref)
+        .ok_or_else(|| RpcError::new(ENGINE_ERROR, format!("{} has no {}", entity, name)))?;
+    let value = match field {
+        "" => value.as_partial_reflect(),
+        field => value.reflect_path(field).map_err(|e| {
+            RpcError::new(INVALID_PARAMS, format!("Cannot access '{}': {}", path, e))
+        })?,
+    };
+
+    serde_json::to_value(TypedReflectSerializer::new(value, &registry))
+        .map_err(|e| RpcError::new(ENGINE_ERROR, e.to_string()))
+}
+
+fn set_component(world: &mut World, params: SetParams) -> Result<Value, RpcError> {
+    let SetParams {
+        entity,
+        path,
+        value,
+    } = params;
+    let registry = world.resource::<AppTypeRegistry>().clone();
+    let registry = registry.read();
+    let (name, field) = split_component_path(&path);
+    let component =
+        reflect_component(&registry, name).map_err(|e| RpcError::new(INVALID_PARAMS, e))?;
+    let entity_mut = world.get_entity_mut(entity).map_err(|_| missing(entity))?;
+
+    let mut reflected = component
+        .reflect_mut(entity_mut)
+        .ok_or_else(|| RpcError::new(ENGINE_ERROR, format!("{} has no {}", entity, name)))?;
+    let target = match field {
+        "" => reflected.as_partial_reflect_mut(),
+        field => reflected.reflect_path_mut(field).map_err(|e| {
+            RpcError::new(INVALID_PARAMS, format!("Cannot access '{}': {}", path, e))
+        })?,
+    };
+
+    let type_info = target
+        .get_represented_type_info()
+        .ok_or_else(|| RpcError::new(ENGINE_ERROR, format!("Type of '{}' is unknown", path)))?;
+    let registration = registry.get(type_info.type_id()).ok_or_else(|| {
+        RpcError::new(
+            ENGINE_ERROR,
+            format!("{} is not registered", type_info.type_path()),
+        )
+    })?;
+    let parsed = TypedReflectDeserializer::new(registration, &registry)
+        .deserialize(value)
+        .map_err(|e| {
+            RpcError::new(
+                INVALID_PARAMS,
+                format!("Cannot parse value as {}: {}", type_info.type_path(), e),
+            )
+        })?;
+
+    target
+        .try_apply(parsed.as_ref())
+        .map_err(|e| RpcError::new(ENGINE_ERROR, format!("Cannot set '{}': {}", path, e)))?;
+    serde_json::to_value(TypedReflectSerializer::new(target, &registry))
+        .map_err(|e| RpcError::new(ENGINE_ERROR, e.to_string()))
+}
+
+fn missing(entity: Entity) -> RpcError {
+    RpcError::new(ENGINE_ERROR, format!("Cannot find entity '{}'", entity))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::ErrorKind;
+    use std::time::Duration;
+
+    use derive_more::derive::{Display, FromStr};
+
+    use super::*;
+    use crate::engine::prototype::PrototypeBundle;
+
+    #[derive(FromStr, Display, Clone, Copy, PartialEq, Eq, Hash)]
+    enum TestId {
+        Dummy,
+    }
+
+    #[derive(Component, Clone, Default, Reflect)]
+    #[reflect(Component)]
+    struct Counter {
+        value: u32,
+    }
+
+    struct Client {
+        reader: BufReader<TcpStream>,
+        writer: TcpStream,
+    }
+
+    impl Client {
+        fn connect(port: u16) -> Self {
+            let stream = TcpStream::connect((Ipv4Addr::LOCALHOST, port)).unwrap();
+            stream.set_nonblocking(true).unwrap();
+            Self {
+                writer: stream.try_clone().unwrap(),
+                reader: BufReader::new(stream),
+            }
+        }
+
+        /// Sends the request and updates the app until the response arrives.
+        fn call(&mut self, app: &mut App, method: &str, params: Value) -> Value {
+            let request = json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params });
+            writeln!(self.writer, "{}", request).unwrap();
+
+            let mut line = String::new();
+            for _ in 0..500 {
+                app.update();
+                match self.reader.read_line(&mut line) {
+                    Ok(_) if line.ends_with('\n') => return serde_json::from_str(&line).unwrap(),
+                    Ok(_) => {}
+                    Err(error) if error.kind() == ErrorKind::WouldBlock => {}
+                    Err(error) => panic!("Cannot read response: {}", error),
+                }
+                thread::sleep(Duration::from_millis(10));
+            }
+            panic!("No response to '{}'", method);
+        }
+    }
+
+    /// App with the remote server on a free port, returned with it.
+    fn create_app() -> (App, u16) {
+        // system picks a free port, it is released right before the server binds it
+        let port = TcpListener::bind((Ipv4Addr::LOCALHOST, 0))
+            .and_then(|listener| listener.local_addr())
+            .unwrap()
+            .port();
+
+        let mut prototypes = bevy::utils::HashMap::new();
+        prototypes.insert(
+            TestId::Dummy,
+            Box::new((Name::new("Dummy"), Counter::default())) as Box<dyn PrototypeBundle<TestId>>,
+        );
+
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins);
+        app.register_type::<Counter>();
+        app.insert_resource(PrototypeRegistry::new(prototypes));
+        app.add_plugins(RemotePlugin::<TestId, TestId>::new(port));
+        (app, port)
+    }
+
+    #[test]
+    fn serves_requests() {
+        let (mut app, port) = create_app();
+        let mut client = Client::connect(port);
+
+        let spawned = client.call(
+            &mut app,
+            "prototype.spawn",
+            json!({ "kind": "character", "id": "Dummy" }),
+        );
+        let entity = spawned["result"].clone();
+        assert!(entity.is_u64(), "{}", spawned);
+
+        let set = client.call(
+            &mut app,
+            "component.set",
+            json!({ "entity": entity, "path": "Counter.value", "value": 7 }),
+        );
+        assert_eq!(set["result"], json!(7), "{}", set);
+
+        let get = client.call(
+            &mut app,
+            "component.get",
+            json!({ "entity": entity, "path": "Counter.value" }),
+        );
+        assert_eq!(get["result"], json!(7), "{}", get);
+
+        let before = app.world().resource::<FrameCount>().0;
+        let step = client.call(&mut app, "frame.step", json!({ "frames": 3 }));
+        assert!(step["result"]["frame"].as_u64().unwrap() >= u64::from(before) + 3);
+    }
+
+    #[test]
+    fn reports_errors() {
+        let (mut app, port) = create_app();
+        let mut client = Client::connect(port);
+
+        let unknown = client.call(&mut app, "entity.explode", Value::Null);
+        assert_eq!(
+            unknown["error"]["code"],
+            json!(METHOD_NOT_FOUND),
+            "{}",
+            unknown
+        );
+
+        let bad_params = client.call(
+            &mut app,
+            "component.get",
+            json!({ "entity": "player", "path": "Counter" }),
+        );
+        assert_eq!(
+            bad_params["error"]["code"],
+            json!(INVALID_PARAMS),
+            "{}",
+            bad_params
+        );
+
+        let bad_id = client.call(
+            &mut app,
+            "prototype.spawn",
+            json!({ "kind": "item", "id": "Dragon" }),
+        );
+        assert_eq!(bad_id["error"]["code"], json!(INVALID_PARAMS), "{}", bad_id);
+    }
+}
@@ -0,0 +1,155 @@
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+
+use bevy::prelude::*;
+use bevy::utils::AHasher;
+use serde::{Deserialize, Serialize};
+
+use super::character::Character;
+use super::input::GameplayInput;
+use super::simulation::Interpolated;
+
+/// Inserted when simulation has to give the same results for the same input,
+/// systems with non-deterministic shortcuts (like background tasks) should avoid them.
+#[derive(Resource, Default, Debug)]
+pub struct DeterministicSimulation;
+
+/// Session recorded step by step, only [`GameplayInput`] is stored,
+/// so anything driven by other input (like console commands) is not reproduced.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Recording {
+    pub seed: u64,
+    /// Frame time of the recorded session, replay uses it unless told otherwise.
+    pub timestep: f64,
+    /// Input of every [`FixedUpdate`] step, so replay does not depend on the frame rate.
+    /// Pressed actions are stored only in the step that used them.
+    pub steps: Vec<GameplayInput>,
+}
+
+impl Recording {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| format!("Cannot read '{}': {}", path.display(), e))?;
+        ron::from_str(&content).map_err(|e| format!("Cannot parse '{}': {}", path.display(), e))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let content =
+            ron::to_string(self).map_err(|e| format!("Cannot serialize recording: {}", e))?;
+        fs::write(path, content).map_err(|e| format!("Cannot write '{}': {}", path.display(), e))
+    }
+}
+
+pub struct RecordPlugin {
+    pub file: PathBuf,
+    pub seed: u64,
+    pub timestep: f64,
+}
+
+impl Plugin for RecordPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(InputRecorder {
+            file: self.file.clone(),
+            recording: Recording {
+                seed: self.seed,
+                timestep: self.timestep,
+                steps: Vec::new(),
+            },
+        });
+        app.add_systems(FixedFirst, record_input);
+        app.add_systems(Last, save_recording_on_exit);
+    }
+}
+
+pub struct ReplayPlugin {
+    pub recording: Recording,
+}
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(InputReplay {
+            steps: self.recording.steps.clone(),
+            step: 0,
+        });
+        app.add_systems(FixedFirst, replay_input);
+        app.add_systems(FixedPostUpdate, finish_replay);
+    }
+}
+
+#[derive(Resource)]
+struct InputRecorder {
+    file: PathBuf,
+    recording: Recording,
+}
+
+#[derive(Resource)]
+struct InputReplay {
+    steps: Vec<GameplayInput>,
+    step: usize,
+}
+
+fn record_input(mut recorder: ResMut<InputRecorder>, input: Res<GameplayInput>) {
+    recorder.recording.steps.push(input.clone());
+}
+
+fn replay_input(mut replay: ResMut<InputReplay>, mut input: ResMut<GameplayInput>) {
+    if let Some(step) = replay.steps.get(replay.step) {
+        *input = step.clone();
+    }
+    replay.step += 1;
+}
+
+fn save_recording_on_exit(
+    recorder: Res<InputRecorder>,
+    exit: EventReader<AppExit>,
+    characters: Query<(Entity, &Interpolated), With<Character>>,
+) {
+    if exit.is_empty() {
+        return;
+    }
+
+    // translations after the last simulation step, not interpolated ones
+    let translations = characters.iter().map(|(e, i)| (e, i.translation()));
+    match recorder.recording.save(&recorder.file) {
+        Ok(()) => info!(
+            "Recorded {} steps to '{}', world checksum {:016x}",
+            recorder.recording.steps.len(),
+            recorder.file.display(),
+            checksum(translations)
+        ),
+        Err(error) => error!("{}", error),
+    }
+}
+
+/// Stops right after the last recorded step, even if the frame has more steps to run.
+fn finish_replay(
+    replay: Res<InputReplay>,
+    characters: Query<(Entity, &Transform), With<Character>>,
+    mut exit: EventWriter<AppExit>,
+) {
+    if replay.step != replay.steps.len() {
+        return;
+    }
+
+    let translations = characters.iter().map(|(e, t)| (e, t.translation));
+    info!(
+        "Replayed {} steps, world checksum {:016x}",
+        replay.step,
+        checksum(translations)
+    );
+    exit.send(AppExit::Success);
+}
+
+/// Hash of character positions, equal for recording and its replay if simulation is deterministic.
+fn checksum(translations: impl Iterator<Item = (Entity, Vec3)>) -> u64 {
+    let mut translations = translations.collect::<Vec<_>>();
+    translations.sort_by_key(|(entity, _)| *entity);
+
+    let mut hasher = AHasher::default();
+    for (entity, translation) in translations {
+        entity.hash(&mut hasher);
+        translation.to_array().map(f32::to_bits).hash(&mut hasher);
+    }
+    hasher.finish()
+}
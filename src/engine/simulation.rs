@@ -0,0 +1,80 @@
+use bevy::prelude::*;
+
+/// Gameplay simulation (movement, AI, combat) runs in [`FixedUpdate`] at this rate,
+/// so its outcome does not depend on the frame rate.
+pub const SIMULATION_RATE: f64 = 64.0;
+
+pub struct SimulationPlugin;
+
+impl Plugin for SimulationPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<Interpolated>();
+        app.insert_resource(Time::<Fixed>::from_hz(SIMULATION_RATE));
+        app.add_systems(
+            RunFixedMainLoop,
+            (
+                restore_simulated_translations.in_set(RunFixedMainLoopSystem::BeforeFixedMainLoop),
+                interpolate_translations.in_set(RunFixedMainLoopSystem::AfterFixedMainLoop),
+            ),
+        );
+        app.add_systems(FixedFirst, store_previous_translations);
+        app.add_systems(FixedLast, store_current_translations);
+    }
+}
+
+/// Translation simulated in fixed steps, rendered between the last two steps.
+/// Simulation systems keep using [`Transform`], between frames it holds interpolated value
+/// that is swapped back before the next steps. Moving the entity outside of the simulation
+/// (like teleport from console) is detected and not interpolated.
+#[derive(Component, Default, Reflect, Debug)]
+pub struct Interpolated {
+    previous: Vec3,
+    current: Vec3,
+    rendered: Option<Vec3>,
+}
+
+impl Interpolated {
+    /// Translation after the last simulation step.
+    pub fn translation(&self) -> Vec3 {
+        self.current
+    }
+}
+
+fn restore_simulated_translations(mut entities: Query<(&mut Transform, &mut Interpolated)>) {
+    for (mut transform, mut interpolated) in entities.iter_mut() {
+        match interpolated.rendered {
+            Some(rendered) if rendered == transform.translation => {
+                transform.translation = interpolated.current;
+            }
+            _ => {
+                // new entity or moved outside of the simulation
+                interpolated.previous = transform.translation;
+                interpolated.current = transform.translation;
+            }
+        }
+    }
+}
+
+fn store_previous_translations(mut entities: Query<(&Transform, &mut Interpolated)>) {
+    for (transform, mut interpolated) in entities.iter_mut() {
+        interpolated.previous = transform.translation;
+    }
+}
+
+fn store_current_translations(mut entities: Query<(&Transform, &mut Interpolated)>) {
+    for (transform, mut interpolated) in entities.iter_mut() {
+        interpolated.current = transform.translation;
+    }
+}
+
+fn interpolate_translations(
+    mut entities: Query<(&mut Transform, &mut Interpolated)>,
+    time: Res<Time<Fixed>>,
+) {
+    let alpha = time.overstep_fraction();
+    for (mut transform, mut interpolated) in entities.iter_mut() {
+        let translation = interpolated.previous.lerp(interpolated.current, alpha);
+        transform.translation = translation;
+        interpolated.rendered = Some(translation);
+    }
+}
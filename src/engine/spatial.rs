@@ -0,0 +1,239 @@
+use bevy::ecs::query::QueryFilter;
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use bevy::transform::TransformSystem;
+use bevy::utils::HashMap;
+
+pub struct SpatialIndexPlugin;
+
+impl Plugin for SpatialIndexPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<SpatialIndexed>();
+        app.init_resource::<SpatialIndex>();
+        app.add_systems(
+            PostUpdate,
+            update_spatial_index.after(TransformSystem::TransformPropagate),
+        );
+    }
+}
+
+/// Marks entities that should be tracked by [`SpatialIndex`] while they have [`GlobalTransform`].
+#[derive(Component, Default, Clone, Reflect, Debug)]
+pub struct SpatialIndexed;
+
+/// Uniform grid over XZ plane, buckets keep their allocations between [`SpatialHash::clear`] calls.
+#[derive(Clone, Debug)]
+pub struct SpatialHash<T> {
+    cell_size: f32,
+    cells: HashMap<IVec2, Vec<(Vec3, T)>>,
+}
+
+impl<T> Default for SpatialHash<T> {
+    fn default() -> Self {
+        Self::new(1.0)
+    }
+}
+
+impl<T> SpatialHash<T> {
+    pub fn new(cell_size: f32) -> Self {
+        Self {
+            cell_size,
+            cells: HashMap::new(),
+        }
+    }
+
+    pub fn cell(&self, position: Vec3) -> IVec2 {
+        IVec2::new(
+            (position.x / self.cell_size).floor() as i32,
+            (position.z / self.cell_size).floor() as i32,
+        )
+    }
+
+    pub fn clear(&mut self, cell_size: f32) {
+        if cell_size != self.cell_size {
+            self.cell_size = cell_size;
+            self.cells.clear();
+        }
+
+        // buckets left empty since the previous clear are not reused, for example behind a crowd
+        self.cells.retain(|_, bucket| !bucket.is_empty());
+        self.cells.values_mut().for_each(Vec::clear);
+    }
+
+    pub fn insert(&mut self, position: Vec3, value: T) {
+        self.cells
+            .entry(self.cell(position))
+            .or_default()
+            .push((position, value));
+    }
+
+    pub fn remove(&mut self, position: Vec3, value: &T)
+    where
+        T: PartialEq,
+    {
+        let cell = self.cell(position);
+        if let Some(bucket) = self.cells.get_mut(&cell) {
+            if let Some(index) = bucket.iter().position(|(_, v)| v == value) {
+                bucket.swap_remove(index);
+            }
+            if bucket.is_empty() {
+                self.cells.remove(&cell);
+            }
+        }
+    }
+
+    /// Iterates over values from cells overlapping a square around `position`,
+    /// values can be further than `radius` so caller should check the exact distance.
+    pub fn nearby(&self, position: Vec3, radius: f32) -> impl Iterator<Item = &(Vec3, T)> {
+        self.in_area(
+            position - Vec3::splat(radius),
+            position + Vec3::splat(radius),
+        )
+    }
+
+    /// Iterates over values from cells overlapping XZ projection of a box.
+    pub fn in_area(&self, min: Vec3, max: Vec3) -> impl Iterator<Item = &(Vec3, T)> {
+        let (min, max) = (self.cell(min), self.cell(max));
+        let area =
+            (max.x as i64 - min.x as i64 + 1).saturating_mul(max.y as i64 - min.y as i64 + 1);
+
+        // large areas, up to infinite ones, are cheaper to scan by occupied buckets
+        let by_cells = area <= self.cells.len() as i64;
+        let cells = by_cells.then(|| {
+            (min.x..=max.x)
+                .flat_map(move |x| (min.y..=max.y).map(move |z| IVec2::new(x, z)))
+                .filter_map(|cell| self.cells.get(&cell))
+        });
+        let buckets = (!by_cells).then(|| {
+            self.cells
+                .iter()
+                .filter(move |(cell, _)| cell.cmpge(min).all() && cell.cmple(max).all())
+                .map(|(_, bucket)| bucket)
+        });
+
+        cells
+            .into_iter()
+            .flatten()
+            .chain(buckets.into_iter().flatten())
+            .flatten()
+    }
+
+    /// Distance from `position` to the furthest point of any occupied cell on XZ plane.
+    pub fn extent_from(&self, position: Vec3) -> f32 {
+        let position = position.xz();
+        self.cells
+            .iter()
+            .filter(|(_, bucket)| !bucket.is_empty())
+            .map(|(cell, _)| {
+                let min = cell.as_vec2() * self.cell_size;
+                let max = min + Vec2::splat(self.cell_size);
+                (position - min).abs().max((position - max).abs()).length()
+            })
+            .fold(0.0, f32::max)
+    }
+}
+
+/// World positions of all [`SpatialIndexed`] entities, updated after transform propagation.
+#[derive(Resource, Debug)]
+pub struct SpatialIndex {
+    hash: SpatialHash<Entity>,
+    positions: HashMap<Entity, Vec3>,
+}
+
+impl Default for SpatialIndex {
+    fn default() -> Self {
+        Self {
+            hash: SpatialHash::new(4.0),
+            positions: HashMap::new(),
+        }
+    }
+}
+
+impl SpatialIndex {
+    fn update(&mut self, entity: Entity, position: Vec3) {
+        if let Some(previous) = self.positions.insert(entity, position) {
+            self.hash.remove(previous, &entity);
+        }
+        self.hash.insert(position, entity);
+    }
+
+    fn remove(&mut self, entity: Entity) {
+        if let Some(previous) = self.positions.remove(&entity) {
+            self.hash.remove(previous, &entity);
+        }
+    }
+}
+
+/// Proximity queries over [`SpatialIndex`] limited to entities matching the filter,
+/// for example `SpatialQuery<With<Item>>`.
+#[derive(SystemParam)]
+pub struct SpatialQuery<'w, 's, F: QueryFilter + 'static = ()> {
+    index: Res<'w, SpatialIndex>,
+    filter: Query<'w, 's, (), F>,
+}
+
+impl<F: QueryFilter + 'static> SpatialQuery<'_, '_, F> {
+    pub fn within_radius(
+        &self,
+        center: Vec3,
+        radius: f32,
+    ) -> impl Iterator<Item = (Entity, Vec3)> + '_ {
+        self.index
+            .hash
+            .nearby(center, radius)
+            .filter(move |(position, _)| position.distance_squared(center) <= radius * radius)
+            .filter(|(_, entity)| self.filter.contains(*entity))
+            .map(|(position, entity)| (*entity, *position))
+    }
+
+    /// Returns up to `count` entities closest to `center` and their distances, nearest first.
+    pub fn nearest(&self, center: Vec3, count: usize, max_distance: f32) -> Vec<(Entity, f32)> {
+        let extent = self.index.hash.extent_from(center);
+        let mut radius = self.index.hash.cell_size.min(max_distance);
+        loop {
+            let mut found = self
+                .within_radius(center, radius)
+                .map(|(entity, position)| (entity, position.distance(center)))
+                .collect::<Vec<_>>();
+
+            if found.len() >= count || radius >= max_distance {
+                found.sort_by(|a, b| a.1.total_cmp(&b.1));
+                found.truncate(count);
+                return found;
+            }
+
+            // once all occupied cells are covered, only the exact distance limit is left,
+            // so even infinite distance ends with a single scan of occupied buckets
+            radius = if radius >= extent {
+                max_distance
+            } else {
+                (radius * 2.0).min(max_distance)
+            };
+        }
+    }
+
+    pub fn contains(&self, entity: Entity) -> bool {
+        self.index.positions.contains_key(&entity) && self.filter.contains(entity)
+    }
+}
+
+fn update_spatial_index(
+    mut index: ResMut<SpatialIndex>,
+    changed: Query<
+        (Entity, &GlobalTransform),
+        (
+            With<SpatialIndexed>,
+            Or<(Changed<GlobalTransform>, Added<SpatialIndexed>)>,
+        ),
+    >,
+    mut removed_markers: RemovedComponents<SpatialIndexed>,
+    mut removed_transforms: RemovedComponents<GlobalTransform>,
+) {
+    for entity in removed_markers.read().chain(removed_transforms.read()) {
+        index.remove(entity);
+    }
+
+    for (entity, transform) in changed.iter() {
+        index.update(entity, transform.translation());
+    }
+}
@@ -0,0 +1,121 @@
+use bevy::input::mouse::{AccumulatedMouseMotion, AccumulatedMouseScroll};
+use bevy::prelude::*;
+use bevy::time::TimeSystem;
+
+use super::input::bindings::{GamepadAxisHistory, InputAction, InputDevices, InputMap};
+use super::input::context::InputContexts;
+use super::input::{GameplayInput, GameplayInputSet};
+
+/// Time scale limits, zero would be a pause that cannot be stepped.
+pub const MIN_TIME_SCALE: f32 = 0.05;
+pub const MAX_TIME_SCALE: f32 = 16.0;
+
+/// Pause, single steps and scale of gameplay time. They are applied to [`Time<Virtual>`],
+/// so [`Update`] and [`FixedUpdate`] systems are affected, while UI, console
+/// and other [`Time<Real>`] users are not.
+pub struct TimeControlPlugin;
+
+impl Plugin for TimeControlPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<TimeControl>();
+        app.init_resource::<TimeControl>();
+        app.add_systems(First, advance_paused_time.after(TimeSystem));
+        app.add_systems(PreUpdate, control_time_with_keys.after(GameplayInputSet));
+    }
+}
+
+#[derive(Resource, Default, Reflect, Debug)]
+#[reflect(Resource)]
+pub struct TimeControl {
+    /// Simulation steps requested while paused, one is run per frame.
+    steps: u32,
+}
+
+impl TimeControl {
+    pub fn toggle_pause(&mut self, time: &mut Time<Virtual>) {
+        if time.is_paused() {
+            time.unpause();
+            self.steps = 0;
+        } else {
+            time.pause();
+        }
+    }
+
+    /// Runs fixed simulation steps, one per frame, gameplay time is paused first if needed.
+    pub fn step(&mut self, time: &mut Time<Virtual>, steps: u32) {
+        time.pause();
+        self.steps += steps;
+    }
+
+    /// Sets scale clamped to supported range and returns it.
+    pub fn set_scale(&mut self, time: &mut Time<Virtual>, scale: f32) -> f32 {
+        let scale = scale.clamp(MIN_TIME_SCALE, MAX_TIME_SCALE);
+        time.set_relative_speed(scale);
+        scale
+    }
+}
+
+/// Lets paused time move by exactly one fixed timestep, so [`FixedUpdate`] runs once
+/// and frame systems see the same delta.
+fn advance_paused_time(
+    mut control: ResMut<TimeControl>,
+    mut virtual_time: ResMut<Time<Virtual>>,
+    mut time: ResMut<Time>,
+    fixed: Res<Time<Fixed>>,
+) {
+    if control.steps == 0 || !virtual_time.is_paused() {
+        return;
+    }
+
+    control.steps -= 1;
+    virtual_time.advance_by(fixed.timestep());
+    *time = virtual_time.as_generic();
+}
+
+fn control_time_with_keys(
+    mut control: ResMut<TimeControl>,
+    mut time: ResMut<Time<Virtual>>,
+    input: Res<GameplayInput>,
+    map: Res<InputMap>,
+    contexts: Res<InputContexts>,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    mouse_motion: Res<AccumulatedMouseMotion>,
+    mouse_scroll: Res<AccumulatedMouseScroll>,
+    gamepads: Query<&Gamepad>,
+    axis_history: Res<GamepadAxisHistory>,
+) {
+    let devices = InputDevices {
+        keyboard: &keyboard,
+        mouse: &mouse,
+        mouse_motion: &mouse_motion,
+        scroll: &mouse_scroll,
+        gamepad: gamepads.iter().next(),
+        previous_axes: &axis_history,
+    };
+    let pressed =
+        |action| contexts.allows(action) && map.just_pressed(action, input.device, &devices);
+
+    if pressed(InputAction::PauseTime) {
+        control.toggle_pause(&mut time);
+        let state = if time.is_paused() {
+            "paused"
+        } else {
+            "resumed"
+        };
+        info!("Time {}", state);
+    }
+    if pressed(InputAction::StepTime) {
+        control.step(&mut time, 1);
+    }
+    if pressed(InputAction::SlowDownTime) {
+        let scale = time.relative_speed() * 0.5;
+        let scale = control.set_scale(&mut time, scale);
+        info!("Time scale set to {}", scale);
+    }
+    if pressed(InputAction::SpeedUpTime) {
+        let scale = time.relative_speed() * 2.0;
+        let scale = control.set_scale(&mut time, scale);
+        info!("Time scale set to {}", scale);
+    }
+}
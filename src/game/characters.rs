@@ -2,7 +2,9 @@ use bevy::prelude::*;
 use bevy::utils::HashMap;
 use derive_more::derive::{Display, FromStr};
 
+use super::factions::GameFactionId;
 use crate::engine::camera::GameCameraTarget;
+use crate::engine::character::faction::Faction;
 use crate::engine::character::npc::Npc;
 use crate::engine::character::player::Player;
 use crate::engine::prototype::{PrototypeBundle, PrototypeRegistry};
@@ -23,6 +25,7 @@ pub fn create_character_registry(
         GameCharacterId::Player,
         Box::new((
             Player,
+            Faction(GameFactionId::Player),
             GameCameraTarget,
             Mesh3d(meshes.add(Capsule3d::new(0.5, 1.0))),
             MeshMaterial3d(materials.add(Color::linear_rgb(1.0, 0.8, 0.0))),
@@ -33,6 +36,7 @@ pub fn create_character_registry(
         GameCharacterId::Enemy,
         Box::new((
             Npc::default(),
+            Faction(GameFactionId::Bandits),
             Mesh3d(meshes.add(Capsule3d::new(0.5, 1.0))),
             MeshMaterial3d(materials.add(Color::linear_rgb(1.0, 0.0, 0.0))),
         )) as Box<dyn PrototypeBundle<GameCharacterId>>,
@@ -0,0 +1,22 @@
+use bevy::prelude::*;
+use derive_more::derive::{Display, FromStr};
+
+use crate::engine::character::faction::{FactionRelations, MIN_REPUTATION};
+
+#[derive(FromStr, Display, Clone, Copy, PartialEq, Eq, Hash, Reflect)]
+pub enum GameFactionId {
+    Player,
+    Bandits,
+}
+
+pub fn create_faction_relations() -> FactionRelations<GameFactionId> {
+    let mut relations = FactionRelations::default();
+
+    relations.set_mutual_reputation(
+        GameFactionId::Player,
+        GameFactionId::Bandits,
+        MIN_REPUTATION,
+    );
+
+    relations
+}
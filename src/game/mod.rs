@@ -1,14 +1,20 @@
 mod characters;
+mod factions;
 mod items;
 
 use bevy::prelude::*;
 use characters::{GameCharacterId, create_character_registry};
+use factions::create_faction_relations;
 use items::{GameItemId, create_item_registry};
 use rand::Rng;
 
 use super::engine::camera::GameCamera;
 use super::engine::character::Speed;
+use super::engine::character::controller::{Collider, Ground};
+use super::engine::interaction::{Interactable, InteractionEvent, InteractionKind};
 use super::engine::item::storage::InsertItemCommand;
+use super::engine::navigation::NavObstacle;
+use super::engine::random::GameRng;
 use super::engine::{GameInfo, create_app};
 use crate::engine::prototype::PrototypeRegistry;
 
@@ -20,9 +26,11 @@ pub fn run() -> AppExit {
         },
         create_character_registry,
         create_item_registry,
+        create_faction_relations,
     );
 
     app.add_systems(Startup, setup);
+    app.add_systems(Update, harvest_rocks);
     app.run()
 }
 
@@ -32,6 +40,7 @@ fn setup(
     mut materials: ResMut<Assets<StandardMaterial>>,
     character_registry: Res<PrototypeRegistry<GameCharacterId>>,
     item_registry: Res<PrototypeRegistry<GameItemId>>,
+    mut rng: ResMut<GameRng>,
 ) {
     commands.spawn(GameCamera::default());
     commands.spawn((
@@ -43,11 +52,35 @@ fn setup(
         Transform::from_xyz(3.0, 10.0, 3.0).looking_at(Vec3::ZERO, Vec3::Y),
     ));
     commands.spawn((
+        Ground,
         Transform::from_xyz(0.0, -1.0, 0.0),
         Mesh3d(meshes.add(Plane3d::new(Vec3::Y, Vec2::new(1000.0, 1000.0)))),
         MeshMaterial3d(materials.add(Color::linear_rgb(0.1, 0.3, 0.1))),
     ));
 
+    let rock = meshes.add(Cuboid::new(4.0, 2.0, 4.0));
+    let rock_material = materials.add(Color::linear_rgb(0.4, 0.4, 0.4));
+    for (x, z) in [
+        (-20.0, 40.0),
+        (15.0, 45.0),
+        (0.0, 30.0),
+        (30.0, 60.0),
+        (-35.0, 55.0),
+    ] {
+        commands.spawn((
+            Name::new("Rock"),
+            NavObstacle,
+            Collider,
+            Interactable {
+                range: 3.5,
+                ..Interactable::new(InteractionKind::Harvest, "Mine")
+            },
+            Transform::from_xyz(x, 0.0, z),
+            Mesh3d(rock.clone()),
+            MeshMaterial3d(rock_material.clone()),
+        ));
+    }
+
     let player = character_registry.spawn(GameCharacterId::Player, &mut commands);
 
     let sword = item_registry.spawn_at(GameItemId::LongSword, Transform::default(), &mut commands);
@@ -58,7 +91,7 @@ fn setup(
 
     item_registry.spawn_at(
         GameItemId::Chestplate,
-        Transform::from_xyz(-10.0, 0.0, 2.5),
+        Transform::from_xyz(-10.0, -1.0, 2.5),
         &mut commands,
     );
 
@@ -72,7 +105,156 @@ fn setup(
 
             commands
                 .entity(enemy)
-                .insert(Speed(rand::thread_rng().gen_range(3.0..10.0)));
+                .insert(Speed(rng.gen_range(3.0..10.0)));
+        }
+    }
+}
+
+fn harvest_rocks(mut events: EventReader<InteractionEvent>, names: Query<&Name>) {
+    for event in events.read() {
+        if event.kind != InteractionKind::Harvest {
+            continue;
+        }
+
+        // temporary until there are resources to harvest
+        if let (Ok(actor), Ok(target)) = (names.get(event.actor), names.get(event.target)) {
+            info!("{} mined {}", actor, target);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::PathBuf;
+    use std::time::Duration;
+
+    use bevy::app::PluginsState;
+    use bevy::time::TimeUpdateStrategy;
+
+    use super::*;
+    use crate::engine::character::Character;
+    use crate::engine::character::player::PointerTarget;
+    use crate::engine::create_test_app;
+    use crate::engine::input::GameplayInput;
+    use crate::engine::replay::DeterministicSimulation;
+
+    /// Simulation steps compared between runs, 3 seconds at the simulation rate.
+    const STEPS: u32 = 192;
+
+    /// Character translations after [`STEPS`] simulation steps.
+    #[derive(Resource, Default)]
+    struct Snapshot {
+        steps: u32,
+        translations: Option<Vec<(Entity, Vec3)>>,
+    }
+
+    fn take_snapshot(
+        mut snapshot: ResMut<Snapshot>,
+        characters: Query<(Entity, &Transform), With<Character>>,
+    ) {
+        snapshot.steps += 1;
+        if snapshot.steps == STEPS {
+            let mut translations = characters
+                .iter()
+                .map(|(entity, transform)| (entity, transform.translation))
+                .collect::<Vec<_>>();
+            translations.sort_by_key(|(entity, _)| *entity);
+            snapshot.translations = Some(translations);
+        }
+    }
+
+    fn create_app(args: &[&str]) -> App {
+        // settings of the developer running tests must not change the scene
+        let settings = test_dir().join("settings.json");
+        let args = args
+            .iter()
+            .copied()
+            .chain(["--settings", settings.to_str().unwrap()])
+            .collect::<Vec<_>>();
+        let mut app = create_test_app(
+            &args,
+            GameInfo {
+                name: env!("CARGO_PKG_NAME"),
+                version: None,
+            },
+            create_character_registry,
+            create_item_registry,
+            create_faction_relations,
+        );
+        app.init_resource::<Snapshot>();
+        app.add_systems(Startup, setup);
+        app.add_systems(FixedLast, take_snapshot);
+
+        while app.plugins_state() == PluginsState::Adding {
+            bevy::tasks::tick_global_task_pools_on_main_thread();
+        }
+        app.finish();
+        app.cleanup();
+        app
+    }
+
+    fn test_dir() -> PathBuf {
+        std::env::temp_dir().join("andromeda-tests")
+    }
+
+    fn run_until_snapshot(app: &mut App) -> Vec<(Entity, Vec3)> {
+        loop {
+            app.update();
+            if let Some(translations) = app
+                .world_mut()
+                .resource_mut::<Snapshot>()
+                .translations
+                .take()
+            {
+                return translations;
+            }
         }
     }
+
+    fn simulate(frame_time: f64) -> Vec<(Entity, Vec3)> {
+        let mut app = create_app(&["--headless", "1000000", "--seed", "7"]);
+        app.insert_resource(DeterministicSimulation);
+        app.insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f64(
+            frame_time,
+        )));
+        run_until_snapshot(&mut app)
+    }
+
+    #[test]
+    fn simulation_does_not_depend_on_frame_rate() {
+        let slow = simulate(1.0 / 30.0);
+        let fast = simulate(1.0 / 144.0);
+
+        assert!(!slow.is_empty());
+        assert_eq!(slow, fast);
+    }
+
+    #[test]
+    fn replay_reproduces_recorded_session() {
+        fs::create_dir_all(test_dir()).unwrap();
+        let file = test_dir().join("session.ron");
+        let file = file.to_str().unwrap();
+
+        let mut app = create_app(&["--headless", "1000000", "--seed", "7", "--record", file]);
+        // click order is followed first, then keyboard movement cancels it
+        app.world_mut().resource_mut::<GameplayInput>().order =
+            Some(PointerTarget::Ground(Vec3::new(10.0, 0.0, 10.0)));
+        for _ in 0..60 {
+            app.update();
+        }
+        app.world_mut()
+            .resource_mut::<ButtonInput<KeyCode>>()
+            .press(KeyCode::KeyW);
+        let recorded = run_until_snapshot(&mut app);
+        // recording is saved on exit
+        app.world_mut().send_event(AppExit::Success);
+        app.update();
+
+        let mut app = create_app(&["--replay", file]);
+        let replayed = run_until_snapshot(&mut app);
+
+        assert!(!recorded.is_empty());
+        assert_eq!(recorded, replayed);
+    }
 }